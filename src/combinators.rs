@@ -1,29 +1,1016 @@
-use crate::parser::Parser;
-
-pub fn many<T: 'static>(a: Parser<T, String>) -> Parser<Vec<T>, String> {
-    Box::new(move |s| {
-        let mut result = vec![];
-        let mut remaining = s.clone();
-        while let Ok((rem, v)) = a(remaining.clone()) {
-            result.push(v);
+use crate::error::{Needed, ParserError};
+use crate::input::{Input, Span};
+use crate::parser::{Assoc, BoxedParser, Parser};
+use crate::profile::profile;
+use crate::trace::trace;
+use std::cell::OnceCell;
+use std::fmt::Display;
+use std::rc::{Rc, Weak};
+
+fn no_progress<I: Input>(input: I) -> ParserError {
+    ParserError::new(
+        input,
+        "Repeated parser succeeded without consuming input".to_string(),
+    )
+    .into_fatal()
+}
+fn repeat<I: Input, P: Parser<I>, A, F: Fn(A, P::Output) -> A>(
+    parser: &P,
+    mut remaining: I,
+    max: usize,
+    mut acc: A,
+    fold: &F,
+) -> Result<(I, A), ParserError> {
+    for _ in 0..max {
+        match parser.parse(remaining) {
+            Ok((rem, _)) if rem.position() == remaining.position() => {
+                return Err(no_progress(remaining))
+            }
+            Ok((rem, value)) => {
+                acc = fold(acc, value);
+                remaining = rem;
+            }
+            Err(error) if !error.is_recoverable() => return Err(error),
+            Err(_) => break,
+        }
+    }
+    Ok((remaining, acc))
+}
+fn push<T>(mut values: Vec<T>, value: T) -> Vec<T> {
+    values.push(value);
+    values
+}
+pub fn fold_many_m_n<I, P, A, N, F>(
+    min: usize,
+    max: usize,
+    parser: P,
+    init: N,
+    fold: F,
+) -> impl Parser<I, Output = A>
+where
+    I: Input,
+    P: Parser<I>,
+    N: Fn() -> A,
+    F: Fn(A, P::Output) -> A,
+{
+    assert!(
+        min <= max,
+        "Repetition minimum {} is greater than its maximum {}",
+        min,
+        max
+    );
+    move |s: I| {
+        let mut acc = init();
+        let mut remaining = s;
+        for _ in 0..min {
+            let (rem, value) = parser.parse(remaining)?;
+            acc = fold(acc, value);
+            remaining = rem;
+        }
+        repeat(&parser, remaining, max.saturating_sub(min), acc, &fold)
+    }
+}
+pub fn fold_many0<I, P, A, N, F>(parser: P, init: N, fold: F) -> impl Parser<I, Output = A>
+where
+    I: Input,
+    P: Parser<I>,
+    N: Fn() -> A,
+    F: Fn(A, P::Output) -> A,
+{
+    fold_many_m_n(0, usize::MAX, parser, init, fold)
+}
+pub fn fold_many1<I, P, A, N, F>(parser: P, init: N, fold: F) -> impl Parser<I, Output = A>
+where
+    I: Input,
+    P: Parser<I>,
+    N: Fn() -> A,
+    F: Fn(A, P::Output) -> A,
+{
+    fold_many_m_n(1, usize::MAX, parser, init, fold)
+}
+#[allow(dead_code)]
+pub fn many<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = Vec<P::Output>> {
+    fold_many0(parser, Vec::new, push)
+}
+#[allow(dead_code)]
+pub fn many1<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = Vec<P::Output>> {
+    fold_many1(parser, Vec::new, push)
+}
+#[allow(dead_code)]
+pub fn many_m_n<I: Input, P: Parser<I>>(
+    min: usize,
+    max: usize,
+    parser: P,
+) -> impl Parser<I, Output = Vec<P::Output>> {
+    fold_many_m_n(min, max, parser, Vec::new, push)
+}
+#[allow(dead_code)]
+pub fn count<I: Input, P: Parser<I>>(
+    n: usize,
+    parser: P,
+) -> impl Parser<I, Output = Vec<P::Output>> {
+    fold_many_m_n(n, n, parser, Vec::new, push)
+}
+#[allow(dead_code)]
+pub fn many_till<I: Input, P: Parser<I>, E: Parser<I>>(
+    parser: P,
+    end: E,
+) -> impl Parser<I, Output = (Vec<P::Output>, E::Output)> {
+    move |s: I| {
+        let mut remaining = s;
+        let mut values = vec![];
+        loop {
+            let error = match end.parse(remaining) {
+                Ok((rem, terminator)) => return Ok((rem, (values, terminator))),
+                Err(error) if !error.is_recoverable() => return Err(error),
+                Err(error) => error,
+            };
+            let (rem, value) = parser
+                .parse(remaining)
+                .map_err(|other| error.merge(other))?;
+            if rem.position() == remaining.position() {
+                return Err(no_progress(remaining));
+            }
+            values.push(value);
             remaining = rem;
         }
-        Ok((remaining, result))
-    })
+    }
+}
+#[allow(dead_code)]
+pub fn sep_by1<I: Input, P: Parser<I>, S: Parser<I>>(
+    parser: P,
+    separator: S,
+) -> impl Parser<I, Output = Vec<P::Output>> {
+    move |s: I| {
+        let (mut remaining, value) = parser.parse(s)?;
+        let mut values = vec![value];
+        loop {
+            match separator.parse(remaining) {
+                Ok((rem, _)) => {
+                    let (rem, value) = parser.parse(rem)?;
+                    if rem.position() == remaining.position() {
+                        return Err(no_progress(remaining));
+                    }
+                    values.push(value);
+                    remaining = rem;
+                }
+                Err(error) if !error.is_recoverable() => return Err(error),
+                Err(_) => return Ok((remaining, values)),
+            }
+        }
+    }
+}
+#[allow(dead_code)]
+pub fn sep_by<I: Input, P: Parser<I>, S: Parser<I>>(
+    parser: P,
+    separator: S,
+) -> impl Parser<I, Output = Vec<P::Output>> {
+    let list = sep_by1(parser, separator);
+    move |s: I| match list.parse(s) {
+        Err(error) if error.is_recoverable() && error.position() == Some(s.position()) => {
+            Ok((s, vec![]))
+        }
+        result => result,
+    }
 }
-pub fn many1<T: 'static>(predicate: Parser<T, String>) -> Parser<Vec<T>, String> {
-    Box::new(move |s| {
+#[allow(dead_code)]
+pub fn sep_end_by<I: Input, P: Parser<I>, S: Parser<I>>(
+    parser: P,
+    separator: S,
+) -> impl Parser<I, Output = Vec<P::Output>> {
+    move |s: I| {
+        let mut remaining = s;
         let mut values = vec![];
-        match predicate(s.clone()) {
-            Ok((mut remaining, val)) => {
-                values.push(val);
-                while let Ok((rem, val)) = predicate(remaining.clone()) {
-                    values.push(val);
+        loop {
+            let start = remaining;
+            match parser.parse(remaining) {
+                Ok((rem, value)) => {
+                    values.push(value);
+                    remaining = rem;
+                }
+                Err(error) if !error.is_recoverable() => return Err(error),
+                Err(_) => return Ok((remaining, values)),
+            }
+            match separator.parse(remaining) {
+                Ok((rem, _)) if rem.position() == start.position() => {
+                    return Err(no_progress(start))
+                }
+                Ok((rem, _)) => remaining = rem,
+                Err(error) if !error.is_recoverable() => return Err(error),
+                Err(_) => return Ok((remaining, values)),
+            }
+        }
+    }
+}
+#[allow(dead_code)]
+pub fn peek<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = P::Output> {
+    move |s: I| {
+        let (_, output) = parser.parse(s)?;
+        Ok((s, output))
+    }
+}
+#[allow(dead_code)]
+pub fn not<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = ()> {
+    move |s: I| match parser.parse(s) {
+        Ok(_) => Err(ParserError::new(s, "Unexpected input".to_string())),
+        Err(error) if !error.is_recoverable() => Err(error),
+        Err(_) => Ok((s, ())),
+    }
+}
+pub fn opt<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = Option<P::Output>> {
+    move |s: I| match parser.parse(s) {
+        Ok((remaining, output)) => Ok((remaining, Some(output))),
+        Err(error) if !error.is_recoverable() => Err(error),
+        Err(_) => Ok((s, None)),
+    }
+}
+pub fn eof<I: Input>() -> impl Parser<I, Output = ()> {
+    |s: I| {
+        if !s.is_empty() {
+            Err(ParserError::expected(s, "end of input".to_string()))
+        } else if s.is_partial() {
+            Err(ParserError::incomplete(s, Needed::Unknown))
+        } else {
+            Ok((s, ()))
+        }
+    }
+}
+pub fn recognize<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = I> {
+    move |s: I| {
+        let (remaining, _) = parser.parse(s)?;
+        Ok((remaining, remaining.consumed_since(s)))
+    }
+}
+pub fn spanned<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = (P::Output, Span)> {
+    move |s: I| {
+        let (remaining, output) = parser.parse(s)?;
+        Ok((
+            remaining,
+            (output, Span::new(s.position(), remaining.position())),
+        ))
+    }
+}
+
+pub fn label<I: Input, P: Parser<I>>(
+    name: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output>
+where
+    I::Item: Display,
+{
+    move |s: I| {
+        parser.parse(s).map_err(|error| {
+            if error.position() == Some(s.position()) {
+                error.with_label(s, name.to_string())
+            } else {
+                error
+            }
+        })
+    }
+}
+pub fn context<I: Input, P: Parser<I>>(
+    name: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output> {
+    move |s: I| parser.parse(s).map_err(|error| error.with_context(name))
+}
+pub fn rule<I: Input, P: Parser<I>>(
+    name: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output>
+where
+    I::Item: Display,
+{
+    trace(name, profile(name, parser))
+}
+pub fn cut<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = P::Output> {
+    move |s: I| parser.parse(s).map_err(ParserError::into_fatal)
+}
+pub fn recover<I: Input, P: Parser<I>, S: Parser<I>>(
+    parser: P,
+    sync: S,
+) -> impl Parser<I, Output = Result<P::Output, ParserError>> {
+    move |s: I| match parser.parse(s) {
+        Ok((remaining, output)) => Ok((remaining, Ok(output))),
+        Err(error) => {
+            let failed_at = error.position().map_or(0, |position| {
+                position.offset.saturating_sub(s.position().offset)
+            });
+            let mut remaining = s.split_at(failed_at).1;
+            loop {
+                if let Ok((rem, _)) = sync.parse(remaining) {
                     remaining = rem;
+                    break;
+                }
+                match remaining.iter_indices().nth(1) {
+                    Some((next, _)) => remaining = remaining.split_at(next).1,
+                    None => {
+                        remaining = remaining.split_at(remaining.input_len()).1;
+                        break;
+                    }
                 }
-                Ok((remaining, values))
             }
-            Err(e) => Err(e),
+            Ok((remaining, Err(error)))
+        }
+    }
+}
+
+pub trait Sequence<I> {
+    type Output;
+
+    fn parse_sequence(&self, input: I) -> Result<(I, Self::Output), ParserError>;
+}
+macro_rules! impl_sequence {
+    ($($parser:ident $output:ident),+) => {
+        impl<I, $($parser: Parser<I>),+> Sequence<I> for ($($parser,)+) {
+            type Output = ($($parser::Output,)+);
+
+            #[allow(non_snake_case)]
+            fn parse_sequence(&self, input: I) -> Result<(I, Self::Output), ParserError> {
+                let ($($parser,)+) = self;
+                $(let (input, $output) = $parser.parse(input)?;)+
+                Ok((input, ($($output,)+)))
+            }
         }
-    })
+    };
+}
+impl_sequence!(A a);
+impl_sequence!(A a, B b);
+impl_sequence!(A a, B b, C c);
+impl_sequence!(A a, B b, C c, D d);
+impl_sequence!(A a, B b, C c, D d, E e);
+impl_sequence!(A a, B b, C c, D d, E e, F f);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g, H h);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g, H h, J j);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g, H h, J j, K k);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g, H h, J j, K k, L l);
+impl_sequence!(A a, B b, C c, D d, E e, F f, G g, H h, J j, K k, L l, M m);
+
+#[allow(dead_code)]
+pub fn tuple<I, T: Sequence<I>>(parsers: T) -> impl Parser<I, Output = T::Output> {
+    move |s: I| parsers.parse_sequence(s)
+}
+#[allow(dead_code)]
+pub fn pair<I, A: Parser<I>, B: Parser<I>>(
+    first: A,
+    second: B,
+) -> impl Parser<I, Output = (A::Output, B::Output)> {
+    tuple((first, second))
+}
+pub fn preceded<I, A: Parser<I>, B: Parser<I>>(
+    prefix: A,
+    parser: B,
+) -> impl Parser<I, Output = B::Output> {
+    prefix.then(parser)
+}
+pub fn terminated<I, A: Parser<I>, B: Parser<I>>(
+    parser: A,
+    suffix: B,
+) -> impl Parser<I, Output = A::Output> {
+    parser.skip(suffix)
+}
+pub fn delimited<I, A: Parser<I>, B: Parser<I>, C: Parser<I>>(
+    open: A,
+    parser: B,
+    close: C,
+) -> impl Parser<I, Output = B::Output> {
+    open.then(parser).skip(close)
+}
+#[allow(dead_code)]
+pub fn separated_pair<I, A: Parser<I>, S: Parser<I>, B: Parser<I>>(
+    first: A,
+    separator: S,
+    second: B,
+) -> impl Parser<I, Output = (A::Output, B::Output)> {
+    tuple((first, separator, second)).map(|(first, _, second)| (first, second))
+}
+
+fn chain_tail<I, O, P, Op, F>(
+    operand: &P,
+    operator: &Op,
+    mut remaining: I,
+) -> Result<(I, Vec<(F, O)>), ParserError>
+where
+    I: Input,
+    P: Parser<I, Output = O>,
+    Op: Parser<I, Output = F>,
+{
+    let mut tail = vec![];
+    loop {
+        match operator.parse(remaining) {
+            Ok((rem, fold)) => {
+                let (rem, right) = operand.parse(rem)?;
+                if rem.position() == remaining.position() {
+                    return Err(no_progress(remaining));
+                }
+                tail.push((fold, right));
+                remaining = rem;
+            }
+            Err(error) if !error.is_recoverable() => return Err(error),
+            Err(_) => return Ok((remaining, tail)),
+        }
+    }
+}
+#[allow(dead_code)]
+pub fn chainl1<I, O, P, Op, F>(operand: P, operator: Op) -> impl Parser<I, Output = O>
+where
+    I: Input,
+    P: Parser<I, Output = O>,
+    Op: Parser<I, Output = F>,
+    F: FnOnce(O, O) -> O,
+{
+    move |s: I| {
+        let (remaining, first) = operand.parse(s)?;
+        let (remaining, tail) = chain_tail(&operand, &operator, remaining)?;
+        Ok((
+            remaining,
+            tail.into_iter()
+                .fold(first, |left, (fold, right)| fold(left, right)),
+        ))
+    }
+}
+#[allow(dead_code)]
+pub fn chainr1<I, O, P, Op, F>(operand: P, operator: Op) -> impl Parser<I, Output = O>
+where
+    I: Input,
+    P: Parser<I, Output = O>,
+    Op: Parser<I, Output = F>,
+    F: FnOnce(O, O) -> O,
+{
+    move |s: I| {
+        let (remaining, first) = operand.parse(s)?;
+        let (remaining, tail) = chain_tail(&operand, &operator, remaining)?;
+        let mut tail = tail.into_iter().rev();
+        let Some((mut pending, mut right)) = tail.next() else {
+            return Ok((remaining, first));
+        };
+        for (fold, left) in tail {
+            right = pending(left, right);
+            pending = fold;
+        }
+        Ok((remaining, pending(first, right)))
+    }
+}
+
+enum Handle<T> {
+    Owned(Rc<T>),
+    Weak(Weak<T>),
+}
+pub struct Recursive<'a, I, O>(Handle<OnceCell<BoxedParser<'a, I, O>>>);
+
+impl<'a, I, O> Clone for Recursive<'a, I, O> {
+    fn clone(&self) -> Self {
+        match &self.0 {
+            Handle::Owned(cell) => Self(Handle::Owned(Rc::clone(cell))),
+            Handle::Weak(cell) => Self(Handle::Weak(Weak::clone(cell))),
+        }
+    }
+}
+impl<'a, I, O> Parser<I> for Recursive<'a, I, O> {
+    type Output = O;
+
+    fn parse(&self, input: I) -> Result<(I, O), ParserError> {
+        let cell = match &self.0 {
+            Handle::Owned(cell) => Rc::clone(cell),
+            Handle::Weak(cell) => cell
+                .upgrade()
+                .expect("Recursive parser used after its definition was dropped"),
+        };
+        let parser = cell
+            .get()
+            .expect("Recursive parser used before its definition was complete");
+        parser.parse(input)
+    }
+}
+pub fn recursive<'a, I, O, P, F>(define: F) -> Recursive<'a, I, O>
+where
+    P: Parser<I, Output = O> + 'a,
+    F: FnOnce(Recursive<'a, I, O>) -> P,
+{
+    let cell = Rc::new(OnceCell::new());
+    let parser = define(Recursive(Handle::Weak(Rc::downgrade(&cell))));
+    let _ = cell.set(Box::new(parser) as BoxedParser<'a, I, O>);
+    Recursive(Handle::Owned(cell))
+}
+
+type PrefixFold<'a, O> = Box<dyn FnOnce(O) -> O + 'a>;
+type InfixFold<'a, O> = Box<dyn FnOnce(O, O) -> O + 'a>;
+
+enum Operation<'a, I, O> {
+    Prefix(BoxedParser<'a, I, PrefixFold<'a, O>>),
+    Postfix(BoxedParser<'a, I, PrefixFold<'a, O>>),
+    Infix(Assoc, BoxedParser<'a, I, InfixFold<'a, O>>),
+}
+pub struct OperatorTable<'a, I, O> {
+    operations: Vec<(i32, Operation<'a, I, O>)>,
+}
+#[allow(dead_code)]
+impl<'a, I: Input + 'a, O: 'a> OperatorTable<'a, I, O> {
+    pub fn new() -> Self {
+        Self { operations: vec![] }
+    }
+    pub fn prefix<P, F>(mut self, precedence: i32, parser: P, fold: F) -> Self
+    where
+        P: Parser<I> + 'a,
+        F: Fn(P::Output, O) -> O + 'a,
+    {
+        let fold = Rc::new(fold);
+        let parser = parser.map(move |op| {
+            let fold = fold.clone();
+            Box::new(move |operand| fold(op, operand)) as PrefixFold<'a, O>
+        });
+        self.operations
+            .push((precedence, Operation::Prefix(parser.boxed())));
+        self
+    }
+    pub fn postfix<P, F>(mut self, precedence: i32, parser: P, fold: F) -> Self
+    where
+        P: Parser<I> + 'a,
+        F: Fn(O, P::Output) -> O + 'a,
+    {
+        let fold = Rc::new(fold);
+        let parser = parser.map(move |op| {
+            let fold = fold.clone();
+            Box::new(move |operand| fold(operand, op)) as PrefixFold<'a, O>
+        });
+        self.operations
+            .push((precedence, Operation::Postfix(parser.boxed())));
+        self
+    }
+    pub fn infix<P, F>(mut self, precedence: i32, assoc: Assoc, parser: P, fold: F) -> Self
+    where
+        P: Parser<I> + 'a,
+        F: Fn(O, P::Output, O) -> O + 'a,
+    {
+        let fold = Rc::new(fold);
+        let parser = parser.map(move |op| {
+            let fold = fold.clone();
+            Box::new(move |left, right| fold(left, op, right)) as InfixFold<'a, O>
+        });
+        self.operations
+            .push((precedence, Operation::Infix(assoc, parser.boxed())));
+        self
+    }
+    fn parse_with<P: Parser<I, Output = O>>(
+        &self,
+        operand: &P,
+        s: I,
+        min_precedence: i32,
+    ) -> Result<(I, O), ParserError> {
+        let mut prefix = None;
+        for (precedence, operation) in self.operations.iter() {
+            if let Operation::Prefix(parser) = operation {
+                match parser.parse(s) {
+                    Ok(op) => {
+                        prefix = Some((*precedence, op));
+                        break;
+                    }
+                    Err(error) if !error.is_recoverable() => return Err(error),
+                    Err(_) => {}
+                }
+            }
+        }
+        let (mut remaining, mut left) = match prefix {
+            Some((precedence, (remaining, fold))) => {
+                let (remaining, operand) = self.parse_with(operand, remaining, precedence)?;
+                (remaining, fold(operand))
+            }
+            None => operand.parse(s)?,
+        };
+        'operators: loop {
+            for (precedence, operation) in self.operations.iter() {
+                if *precedence < min_precedence {
+                    continue;
+                }
+                match operation {
+                    Operation::Postfix(parser) => match parser.parse(remaining) {
+                        Ok((rem, _)) if rem.position() == remaining.position() => {
+                            return Err(no_progress(remaining))
+                        }
+                        Ok((rem, fold)) => {
+                            remaining = rem;
+                            left = fold(left);
+                            continue 'operators;
+                        }
+                        Err(error) if !error.is_recoverable() => return Err(error),
+                        Err(_) => {}
+                    },
+                    Operation::Infix(assoc, parser) => match parser.parse(remaining) {
+                        Ok((rem, fold)) => {
+                            let next_precedence = match assoc {
+                                Assoc::Right => *precedence,
+                                Assoc::Left | Assoc::Both => precedence + 1,
+                            };
+                            let (rem, right) = self.parse_with(operand, rem, next_precedence)?;
+                            if rem.position() == remaining.position() {
+                                return Err(no_progress(remaining));
+                            }
+                            remaining = rem;
+                            left = fold(left, right);
+                            continue 'operators;
+                        }
+                        Err(error) if !error.is_recoverable() => return Err(error),
+                        Err(_) => {}
+                    },
+                    Operation::Prefix(_) => {}
+                }
+            }
+            return Ok((remaining, left));
+        }
+    }
+}
+pub fn expression<'a, I: Input + 'a, O: 'a, P: Parser<I, Output = O> + 'a>(
+    operand: P,
+    table: OperatorTable<'a, I, O>,
+) -> impl Parser<I, Output = O> + 'a {
+    move |s: I| table.parse_with(&operand, s, i32::MIN)
+}
+
+#[test]
+fn slices() {
+    use crate::common::{take_cpredicate, take_predicate1};
+    use crate::input::Located;
+
+    let bytes = Located::new(&b"\x01\x02\xff"[..]);
+    let (remaining, small) = many1(take_cpredicate(|b: &u8| *b < 0x10))
+        .parse(bytes)
+        .unwrap();
+    assert_eq!(small, vec![1, 2]);
+    assert_eq!(remaining.position().offset, 2);
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Num(i32),
+        Plus,
+    }
+    let tokens = [Token::Num(1), Token::Plus, Token::Num(2)];
+    let (remaining, num) = take_predicate1(|t: &Token| matches!(t, Token::Num(_)))
+        .parse(Located::new(&tokens[..]))
+        .unwrap();
+    assert_eq!(num.fragment(), &[Token::Num(1)][..]);
+    assert_eq!(remaining.fragment(), &tokens[1..]);
+    assert!(many1(take_cpredicate(|t: &Token| *t == Token::Num(3)))
+        .parse(remaining)
+        .is_err());
+}
+
+#[test]
+fn positions() {
+    use crate::common::{take_identifier, take_predicate};
+    use crate::input::{Located, Position};
+    use crate::parser::build_ast;
+
+    let (_, (_, span)) = take_predicate(|c: &char| !c.is_ascii_alphabetic())
+        .then(spanned(take_identifier()))
+        .parse(Located::new("\n  é\n  foo"))
+        .unwrap();
+    assert_eq!(
+        span,
+        Span::new(
+            Position {
+                offset: 8,
+                line: 3,
+                column: 3
+            },
+            Position {
+                offset: 11,
+                line: 3,
+                column: 6
+            }
+        )
+    );
+    let error = build_ast()
+        .parse(Located::new("(1 +\n  2 * 3"))
+        .unwrap_err();
+    assert_eq!(
+        error.position(),
+        Some(Position {
+            offset: 12,
+            line: 2,
+            column: 8
+        })
+    );
+}
+
+#[test]
+fn labels() {
+    use crate::common::take_char;
+    use crate::cst::take_var;
+    use crate::input::Located;
+    use crate::parser::take_statement;
+
+    let error = take_var().parse(Located::new("let 5 = 1")).unwrap_err();
+    assert_eq!(
+        error.message(),
+        "Expected identifier, found `5` while parsing let binding"
+    );
+    let error = take_statement().parse(Located::new("foo;")).unwrap_err();
+    assert_eq!(error.message(), "Expected `let`, number or `(`, found `f`");
+    let error = context("a", take_char('a'))
+        .or(context("b", take_char('b')))
+        .parse(Located::new("c"))
+        .unwrap_err();
+    assert!(error.context().is_empty());
+    let error = take_var().parse(Located::new("let x = +")).unwrap_err();
+    assert_eq!(
+        error.message(),
+        "Expected number or `(`, found `+` while parsing let binding"
+    );
+}
+
+#[test]
+fn commit() {
+    use crate::common::take_char;
+    use crate::input::Located;
+
+    let committed = || take_char('a').then(cut(take_char('b')));
+    let error = committed()
+        .or(take_char('a').then(take_char('c')))
+        .parse(Located::new("ac"))
+        .unwrap_err();
+    assert!(error.is_fatal());
+    assert_eq!(error.message(), "Expected `b`, found `c`");
+    assert!(many(committed()).parse(Located::new("abac")).is_err());
+    assert!(many(committed()).parse(Located::new("abc")).is_ok());
+    let error = tuple((
+        take_char('a'),
+        take_char('b'),
+        take_char('c'),
+        take_char('d'),
+    ))
+    .map(|_| ())
+    .or(take_char('a').then(cut(take_char('x'))).map(|_| ()))
+    .or(take_char('a').map(|_| ()))
+    .parse(Located::new("abcX"))
+    .unwrap_err();
+    assert!(error.is_fatal());
+    assert_eq!(error.message(), "Expected `x`, found `b`");
+}
+
+#[test]
+fn precedence() {
+    use crate::common::{take_char, take_predicate1};
+    use crate::input::Located;
+
+    let table = || {
+        OperatorTable::new()
+            .infix(1, Assoc::Left, take_char('+'), |l, _, r| l + r)
+            .infix(1, Assoc::Left, take_char('-'), |l, _, r| l - r)
+            .infix(2, Assoc::Left, take_char('*'), |l, _, r| l * r)
+            .infix(3, Assoc::Right, take_char('^'), |l: i64, _, r| {
+                l.pow(r as u32)
+            })
+            .prefix(4, take_char('-'), |_, operand: i64| -operand)
+            .postfix(5, take_char('!'), |operand: i64, _| (1..=operand).product())
+    };
+    let number = || {
+        take_predicate1(|c: &char| c.is_ascii_digit())
+            .map(|digits: Located<&str>| digits.fragment().parse::<i64>().unwrap())
+    };
+    let eval = |source| {
+        expression(number(), table())
+            .parse(Located::new(source))
+            .map(|(_, value)| value)
+    };
+    assert_eq!(eval("2^3^2"), Ok(512));
+    assert_eq!(eval("-2*3+1"), Ok(-5));
+    assert_eq!(eval("10-4-3"), Ok(3));
+    assert_eq!(eval("2*3!+1"), Ok(13));
+    assert!(eval("1+").is_err());
+    let error = expression(
+        number(),
+        OperatorTable::new().postfix(1, opt(take_char('!')), |operand: i64, _| operand),
+    )
+    .parse(Located::new("3"))
+    .unwrap_err();
+    assert!(error.is_fatal());
+    let error = expression(
+        opt(number()).map(|number| number.unwrap_or(0)),
+        OperatorTable::new().infix(1, Assoc::Left, opt(take_char('+')), |l, _, r| l + r),
+    )
+    .parse(Located::new("x"))
+    .unwrap_err();
+    assert!(error.is_fatal());
+}
+
+#[test]
+fn sequences() {
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let (remaining, digits) = tuple((
+        take_digit(),
+        take_digit(),
+        take_digit(),
+        take_digit(),
+        take_digit(),
+        take_digit(),
+        take_digit(),
+        take_digit(),
+    ))
+    .parse(Located::new("12345678!"))
+    .unwrap();
+    assert_eq!(digits, ('1', '2', '3', '4', '5', '6', '7', '8'));
+    assert_eq!(remaining.fragment(), "!");
+    let point = delimited(
+        take_char('('),
+        separated_pair(take_digit(), take_char(','), take_digit()),
+        take_char(')'),
+    );
+    assert_eq!(
+        point.parse(Located::new("(1,2)")).map(|(_, point)| point),
+        Ok(('1', '2'))
+    );
+    assert_eq!(
+        pair(
+            preceded(take_char('-'), take_digit()),
+            terminated(take_digit(), take_char(';'))
+        )
+        .parse(Located::new("-12;"))
+        .map(|(_, digits)| digits),
+        Ok(('1', '2'))
+    );
+}
+
+#[test]
+fn separated() {
+    use crate::common::{take_char, take_digit, take_whitespaces};
+    use crate::input::Located;
+
+    let list = |s| sep_by(take_digit(), take_char(',')).parse(Located::new(s));
+    assert_eq!(
+        list("1,2,3)").map(|(_, digits)| digits),
+        Ok(vec!['1', '2', '3'])
+    );
+    assert_eq!(list(")").map(|(_, digits)| digits), Ok(vec![]));
+    let error = list("1,2,)").unwrap_err();
+    assert_eq!(error.position().map(|position| position.offset), Some(4));
+    assert_eq!(error.message(), "Expected a digit, found `)`");
+    assert!(sep_by1(take_digit(), take_char(','))
+        .parse(Located::new(")"))
+        .is_err());
+    let (remaining, digits) = sep_end_by(take_digit(), take_char(';'))
+        .parse(Located::new("1;2;)"))
+        .unwrap();
+    assert_eq!(digits, vec!['1', '2']);
+    assert_eq!(remaining.fragment(), ")");
+    let error = sep_end_by(take_whitespaces(), opt(take_char(',')))
+        .parse(Located::new("1"))
+        .unwrap_err();
+    assert!(error.is_fatal());
+    let error = sep_by(take_whitespaces(), opt(take_char(',')))
+        .parse(Located::new("1"))
+        .unwrap_err();
+    assert!(error.is_fatal());
+}
+
+#[test]
+fn repetitions() {
+    use crate::common::{take_char, take_digit, take_str, take_whitespaces};
+    use crate::input::Located;
+
+    let digits = |min, max, s| {
+        many_m_n(min, max, take_digit())
+            .parse(Located::new(s))
+            .map(|(remaining, digits)| (remaining.fragment(), digits.len()))
+    };
+    assert_eq!(digits(2, 3, "12345"), Ok(("45", 3)));
+    assert_eq!(digits(2, 3, "12a"), Ok(("a", 2)));
+    assert!(digits(2, 3, "1a").is_err());
+    assert!(count(3, take_digit()).parse(Located::new("12a")).is_err());
+    let sum = fold_many0(
+        take_digit(),
+        || 0,
+        |sum, digit| sum + digit.to_digit(10).unwrap(),
+    );
+    assert_eq!(sum.parse(Located::new("1234")).map(|(_, sum)| sum), Ok(10));
+    let comment = preceded(
+        take_str("/*".to_string()),
+        many_till(take_digit().or(take_char(' ')), take_str("*/".to_string())),
+    );
+    let (remaining, (body, _)) = comment.parse(Located::new("/* 1 2 */3")).unwrap();
+    assert_eq!(body, vec![' ', '1', ' ', '2', ' ']);
+    assert_eq!(remaining.fragment(), "3");
+    let error = comment.parse(Located::new("/* 1 x")).unwrap_err();
+    assert_eq!(error.message(), "Expected `*/`, a digit or ` `, found `x`");
+    let error = many(take_whitespaces())
+        .parse(Located::new("1"))
+        .unwrap_err();
+    assert!(error.is_fatal());
+    assert_eq!(
+        error.message(),
+        "Repeated parser succeeded without consuming input"
+    );
+}
+
+#[test]
+#[should_panic(expected = "Repetition minimum 3 is greater than its maximum 2")]
+fn inverted_bounds() {
+    many_m_n(
+        3,
+        2,
+        crate::common::take_digit::<crate::input::Located<&str>>(),
+    );
+}
+
+#[test]
+fn lookahead() {
+    use crate::common::{take_char, take_cpredicate, take_digit, take_str};
+    use crate::input::Located;
+    use crate::parser::build_ast;
+
+    let (remaining, digit) = peek(take_digit()).parse(Located::new("1a")).unwrap();
+    assert_eq!((remaining.fragment(), digit), ("1a", '1'));
+    let keyword = terminated(
+        take_str("let".to_string()),
+        not(take_cpredicate(|c: &char| c.is_ascii_alphanumeric())),
+    );
+    assert!(keyword.parse(Located::new("let x")).is_ok());
+    assert!(keyword.parse(Located::new("letter")).is_err());
+    let sign = opt(take_char('-'));
+    assert_eq!(
+        sign.parse(Located::new("-1")).map(|(_, sign)| sign),
+        Ok(Some('-'))
+    );
+    assert_eq!(
+        sign.parse(Located::new("1")).map(|(_, sign)| sign),
+        Ok(None)
+    );
+    let error = build_ast().parse(Located::new("1 + 2 )")).unwrap_err();
+    assert_eq!(error.position().map(|position| position.offset), Some(6));
+    assert_eq!(error.message(), "Expected end of input");
+}
+
+#[test]
+fn chains() {
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let digit = || take_digit().map(|digit| digit.to_digit(10).unwrap() as i32);
+    let sub = || take_char('-').map(|_| |left: i32, right: i32| left - right);
+    let expr = chainl1(
+        chainl1(
+            digit(),
+            take_char('*').map(|_| |left: i32, right: i32| left * right),
+        ),
+        take_char('+').map(|_| |left: i32, right: i32| left + right),
+    );
+    assert_eq!(
+        expr.parse(Located::new("1+2*3+4")).map(|(_, value)| value),
+        Ok(11)
+    );
+    let left = chainl1(digit(), sub());
+    assert_eq!(
+        left.parse(Located::new("8-4-2")).map(|(_, value)| value),
+        Ok(2)
+    );
+    let right = chainr1(digit(), sub());
+    assert_eq!(
+        right.parse(Located::new("8-4-2")).map(|(_, value)| value),
+        Ok(6)
+    );
+    assert_eq!(
+        right.parse(Located::new("8")).map(|(_, value)| value),
+        Ok(8)
+    );
+    let empty = chainl1(
+        opt(digit()).map(|digit| digit.unwrap_or(0)),
+        opt(sub()).map(|_| |left: i32, right: i32| left + right),
+    );
+    let error = empty.parse(Located::new("x")).unwrap_err();
+    assert!(error.is_fatal());
+    assert_eq!(
+        error.message(),
+        "Repeated parser succeeded without consuming input"
+    );
+    let error = left.parse(Located::new("8-")).unwrap_err();
+    assert_eq!(error.message(), "Expected a digit, found nothing");
+}
+
+#[test]
+fn recursion() {
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let depth = recursive(|list| {
+        delimited(
+            take_char('['),
+            sep_by(take_digit().map(|_| 0).or(list), take_char(',')),
+            take_char(']'),
+        )
+        .map(|items| items.into_iter().max().unwrap_or(0) + 1)
+    });
+    assert_eq!(
+        depth.parse(Located::new("[]")).map(|(_, depth)| depth),
+        Ok(1)
+    );
+    assert_eq!(
+        depth
+            .parse(Located::new("[1,[2,[]],[3]]"))
+            .map(|(_, depth)| depth),
+        Ok(3)
+    );
+    let error = depth.parse(Located::new("[1,[2,]]")).unwrap_err();
+    assert_eq!(error.position().map(|position| position.offset), Some(6));
 }
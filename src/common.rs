@@ -1,154 +1,198 @@
-use crate::combinators::{many, many1};
-
-use crate::error::ParserError;
+use crate::combinators::{label, not};
+use crate::error::{Needed, ParserError};
+use crate::input::{Input, Located};
 use crate::parser::Parser;
-pub fn take_digit() -> Parser<char, String> {
-    Box::new(|s| {
-        if s.len() == 0 {
-            return Err(ParserError::new(
-                s,
-                "Expected a digit, found nothing".to_string(),
-            ));
-        }
-        let mut chars = s.chars();
-        let next = chars.next().unwrap();
-        match next {
-            '0'..='9' => Ok((chars.collect(), next)),
-            _ => Err(ParserError::new(
-                s,
-                format!("Expected a digit, found: `{}`", next),
-            )),
-        }
-    })
+
+fn take_first<I: Input>(s: I) -> Option<(I, I::Item)> {
+    let mut indices = s.iter_indices();
+    let (_, next) = indices.next()?;
+    let end = indices.next().map_or(s.input_len(), |(i, _)| i);
+    Some((s.split_at(end).1, next))
+}
+fn expected_char<I: Input<Item = char>>(s: I, expected: String) -> ParserError {
+    match take_first(s) {
+        Some((_, next)) => ParserError::expected(s, expected).with_found(format!("`{}`", next)),
+        None if s.is_partial() => ParserError::incomplete(s, Needed::Size(1)),
+        None => ParserError::expected(s, expected),
+    }
+}
+#[allow(dead_code)]
+pub fn take_digit<I: Input<Item = char>>() -> impl Parser<I, Output = char> {
+    |s: I| match take_first(s) {
+        Some((remaining, next @ '0'..='9')) => Ok((remaining, next)),
+        _ => Err(expected_char(s, "a digit".to_string())),
+    }
 }
 
 #[allow(dead_code)]
-pub fn take_alpha() -> Parser<char, String> {
-    Box::new(|s| {
-        if s.len() == 0 {
-            return Err(ParserError::new(
-                s,
-                "Expected an alphabetic char, found nothing".to_string(),
-            ));
-        }
-        let mut chars = s.chars();
-        let next = chars.next().unwrap();
-        match next {
-            'A'..='z' => Ok((chars.collect(), next)),
-            _ => Err(ParserError::new(
-                s,
-                format!("Expected an alphabetic char, found: `{}`", next),
-            )),
-        }
-    })
+pub fn take_alpha<I: Input<Item = char>>() -> impl Parser<I, Output = char> {
+    |s: I| match take_first(s) {
+        Some((remaining, next @ 'A'..='z')) => Ok((remaining, next)),
+        _ => Err(expected_char(s, "an alphabetic char".to_string())),
+    }
 }
-pub fn take_char(c: char) -> Parser<char, String> {
-    Box::new(move |s| {
-        if s.len() == 0 {
-            return Err(ParserError::new(
-                s,
-                format!("Expected `{}`, found nothing", c),
-            ));
-        }
-        let mut chars = s.chars();
-        let next = chars.next().unwrap();
-        if next == c {
-            Ok((chars.collect(), next))
-        } else {
-            Err(ParserError::new(
-                s,
-                format!("Expected `{}`, found `{}`", c, next),
-            ))
-        }
-    })
+pub fn take_char<I: Input<Item = char>>(c: char) -> impl Parser<I, Output = char> {
+    move |s: I| match take_first(s) {
+        Some((remaining, next)) if next == c => Ok((remaining, next)),
+        _ => Err(expected_char(s, format!("`{}`", c))),
+    }
 }
-pub fn take_cpredicate(predicate: Box<Fn(char) -> bool>) -> Parser<char, String> {
-    Box::new(move |s| {
-        if s.len() == 0 {
-            return Err(ParserError::new_no_reason(s));
-        }
-        let mut chars = s.chars();
-        let next = chars.next().unwrap();
-        if predicate(next) {
-            Ok((chars.collect(), next))
-        } else {
-            Err(ParserError::new_no_reason(s))
+pub fn take_cpredicate<I: Input, F: Fn(&I::Item) -> bool>(
+    predicate: F,
+) -> impl Parser<I, Output = I::Item> {
+    move |s: I| match take_first(s) {
+        Some((remaining, next)) if predicate(&next) => Ok((remaining, next)),
+        None if s.is_partial() => Err(ParserError::incomplete(s, Needed::Size(1))),
+        _ => Err(ParserError::new_no_reason(s)),
+    }
+}
+pub const RESERVED_WORDS: [&str; 1] = ["let"];
+
+fn is_identifier_char(c: &char) -> bool {
+    c.is_ascii_alphanumeric()
+}
+pub fn take_identifier<I: Input<Item = char>>() -> impl Parser<I, Output = String> {
+    label("identifier", |s: I| {
+        let (remaining, _) = take_cpredicate(|c: &char| c.is_ascii_alphabetic()).parse(s)?;
+        let (remaining, _) = take_predicate(is_identifier_char).parse(remaining)?;
+        let identifier: String = remaining.consumed_since(s).iter_elements().collect();
+        if RESERVED_WORDS.contains(&identifier.as_str()) {
+            return Err(ParserError::expected(s, "identifier".to_string())
+                .with_found(format!("keyword `{}`", identifier)));
         }
+        Ok((remaining, identifier))
     })
 }
-pub fn take_identifier() -> Parser<String, String> {
-    Box::new(|s| {
-        take_cpredicate(Box::new(|c| c.is_ascii_alphabetic()))(s)
-            .map(|(remaining, c)| (remaining, c.to_string()))
-            .and_then(|(remaining, mut identifier)| {
-                let (remaining, ident) = take_predicate1(|c| c.is_ascii_alphanumeric())(remaining)?;
-                identifier.push_str(&ident);
-                Ok((remaining, identifier))
-            })
-    })
+pub fn take_predicate<I: Input, F: Fn(&I::Item) -> bool>(
+    predicate: F,
+) -> impl Parser<I, Output = I> {
+    move |s: I| {
+        let end = match s.iter_indices().find(|(_, c)| !predicate(c)) {
+            Some((end, _)) => end,
+            None if s.is_partial() => return Err(ParserError::incomplete(s, Needed::Unknown)),
+            None => s.input_len(),
+        };
+        let (matched, remaining) = s.split_at(end);
+        Ok((remaining, matched))
+    }
 }
-pub fn take_predicate1<T: 'static + Fn(char) -> bool>(predicate: T) -> Parser<String, String> {
-    Box::new(move |s| {
-        if s.len() == 0 {
+pub fn take_predicate1<I: Input, F: Fn(&I::Item) -> bool>(
+    predicate: F,
+) -> impl Parser<I, Output = I> {
+    let predicate = take_predicate(predicate);
+    move |s: I| {
+        let (remaining, matched) = predicate.parse(s)?;
+        if matched.is_empty() {
             return Err(ParserError::new_no_reason(s));
         }
-        let mut values = vec![];
-        let mut chars = s.chars();
-        let mut next = chars.next().unwrap();
-        while predicate(next) {
-            values.push(next);
-            next = match chars.next() {
-                Some(x) => x,
-                None => return Ok((chars.collect(), values.into_iter().collect())),
-            };
-        }
-        Ok((chars.collect(), values.into_iter().collect()))
-    })
+        Ok((remaining, matched))
+    }
 }
-pub fn take_whitespaces() -> Parser<Vec<char>, String> {
-    Box::new(|s| many(take_cpredicate(Box::new(|c: char| c.is_whitespace())))(s))
+pub fn take_whitespaces<I: Input<Item = char>>() -> impl Parser<I, Output = I> {
+    take_predicate(|c: &char| c.is_whitespace())
 }
-pub fn take_whitespaces1() -> Parser<Vec<char>, String> {
-    Box::new(|s| many1(take_cpredicate(Box::new(|c: char| c.is_whitespace())))(s))
+pub fn take_whitespaces1<I: Input<Item = char>>() -> impl Parser<I, Output = I> {
+    take_predicate1(|c: &char| c.is_whitespace())
 }
-pub fn take_str(str_to_match: String) -> Parser<String, String> {
-    Box::new(move |s| {
-        if str_to_match.len() > s.len() {
-            return Err(ParserError::new(
-                s.clone(),
-                format!("Expected `{}` found `{}`", str_to_match, s),
-            ));
+pub fn take_str<'a>(str_to_match: String) -> impl Parser<Located<&'a str>, Output = &'a str> {
+    move |s: Located<&'a str>| {
+        if s.fragment().starts_with(&str_to_match) {
+            let (matched, remaining) = s.split_at(str_to_match.len());
+            Ok((remaining, matched.fragment()))
+        } else if s.is_partial() && str_to_match.starts_with(s.fragment()) {
+            let needed = str_to_match.len() - s.input_len();
+            Err(ParserError::incomplete(s, Needed::Size(needed)))
+        } else {
+            Err(expected_char(s, format!("`{}`", str_to_match)))
         }
-        let mut schars = s.chars();
-        let chars_to_match = str_to_match.chars();
-        for i in chars_to_match.into_iter() {
-            if i != schars.next().unwrap() {
-                return Err(ParserError::new(
-                    s.clone(),
-                    format!(
-                        "Expected `{}` found `{}`",
-                        str_to_match,
-                        &s[..str_to_match.len() - 1]
-                    ),
-                ));
+    }
+}
+pub fn keyword<'a>(word: &'static str) -> impl Parser<Located<&'a str>, Output = &'a str> {
+    let matches = take_str(word.to_string());
+    let boundary = not(take_cpredicate(is_identifier_char));
+    move |s: Located<&'a str>| {
+        let (remaining, matched) = matches.parse(s)?;
+        match boundary.parse(remaining) {
+            Ok(_) => Ok((remaining, matched)),
+            Err(error) if !error.is_recoverable() => Err(error),
+            Err(_) => {
+                let identifier: String = s.iter_elements().take_while(is_identifier_char).collect();
+                Err(ParserError::expected(s, format!("`{}`", word))
+                    .with_found(format!("identifier `{}`", identifier)))
             }
         }
-        Ok((schars.collect(), str_to_match.to_string()))
-    })
+    }
 }
-
-pub fn take_one_of(strings: Vec<&'static str>) -> Parser<String, String> {
-    Box::new(move |s| {
-        let mut remaining = s;
+pub fn take_one_of<'a>(
+    strings: Vec<&'static str>,
+) -> impl Parser<Located<&'a str>, Output = &'a str> {
+    move |s: Located<&'a str>| {
         for i in strings.iter() {
-            match take_str(i.to_string())(remaining) {
-                Ok((remaining, matched)) => return Ok((remaining, matched)),
-                Err(s) => remaining = s.remaining(),
+            if s.fragment().starts_with(i) {
+                let (matched, remaining) = s.split_at(i.len());
+                return Ok((remaining, matched.fragment()));
             }
         }
-        Err(ParserError::new(
-            remaining,
-            format!("Expected one of the {:#?}", strings),
-        ))
-    })
+        if s.is_partial() && strings.iter().any(|i| i.starts_with(s.fragment())) {
+            return Err(ParserError::incomplete(s, Needed::Unknown));
+        }
+        Err(strings
+            .iter()
+            .map(|i| expected_char(s, format!("`{}`", i)))
+            .reduce(ParserError::merge)
+            .unwrap_or_else(|| ParserError::new_no_reason(s)))
+    }
+}
+
+#[test]
+fn partial() {
+    use crate::parser::build_ast;
+
+    let error = take_str("let".to_string())
+        .parse(Located::partial("le"))
+        .unwrap_err();
+    assert_eq!(error.needed(), Some(Needed::Size(1)));
+    assert!(take_str("let".to_string())
+        .parse(Located::new("le"))
+        .is_err_and(|error| !error.is_incomplete()));
+    assert!(take_digit()
+        .parse(Located::partial(""))
+        .is_err_and(|error| error.is_incomplete()));
+    assert!(build_ast()
+        .parse(Located::partial("(1 +\n  2"))
+        .is_err_and(|error| error.is_incomplete()));
+    assert!(build_ast()
+        .parse(Located::partial("1 + )"))
+        .is_err_and(|error| !error.is_incomplete()));
+}
+
+#[test]
+fn keywords() {
+    use crate::cst::take_var;
+
+    let (remaining, _) = keyword("let").parse(Located::new("let x")).unwrap();
+    assert_eq!(remaining.fragment(), " x");
+    assert!(keyword("let").parse(Located::new("let(")).is_ok());
+    let error = keyword("let")
+        .parse(Located::new("lettuce = 1"))
+        .unwrap_err();
+    assert!(error.is_recoverable());
+    assert_eq!(
+        error.message(),
+        "Expected `let`, found identifier `lettuce`"
+    );
+    assert_eq!(
+        take_identifier()
+            .parse(Located::new("lettuce"))
+            .map(|(_, id)| id),
+        Ok("lettuce".to_string())
+    );
+    let error = take_identifier().parse(Located::new("let")).unwrap_err();
+    assert_eq!(error.message(), "Expected identifier, found keyword `let`");
+    let error = take_var().parse(Located::new("let let = 1")).unwrap_err();
+    assert!(error.is_fatal());
+    assert_eq!(
+        error.message(),
+        "Expected identifier, found keyword `let` while parsing let binding"
+    );
 }
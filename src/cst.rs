@@ -0,0 +1,296 @@
+use crate::combinators::{
+    context, cut, eof, expression, many, pair, preceded, recognize, recursive, rule, spanned,
+    terminated, tuple, OperatorTable,
+};
+use crate::common::{
+    keyword, take_char, take_identifier, take_predicate, take_str, take_whitespaces1,
+};
+use crate::input::{Input, Located, Span};
+use crate::math::{take_number, OPERATORS};
+use crate::parser::{Assoc, Bin, Expr, Literal, Number, Operator, Parser, Var};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub leading: Vec<Trivia>,
+    pub text: String,
+    pub span: Span,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Number {
+        token: Token,
+        value: Number,
+    },
+    Parenthesized {
+        open: Token,
+        expr: Box<Node>,
+        close: Token,
+    },
+    Binary {
+        left: Box<Node>,
+        op: Token,
+        right: Box<Node>,
+    },
+    Let {
+        keyword: Token,
+        identifier: Token,
+        equals: Token,
+        value: Box<Node>,
+    },
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Root {
+    pub node: Node,
+    pub trailing: Vec<Trivia>,
+}
+
+impl fmt::Display for Trivia {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for trivia in self.leading.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        write!(f, "{}", self.text)
+    }
+}
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number { token, .. } => write!(f, "{}", token),
+            Self::Parenthesized { open, expr, close } => write!(f, "{}{}{}", open, expr, close),
+            Self::Binary { left, op, right } => write!(f, "{}{}{}", left, op, right),
+            Self::Let {
+                keyword,
+                identifier,
+                equals,
+                value,
+            } => write!(f, "{}{}{}{}", keyword, identifier, equals, value),
+        }
+    }
+}
+impl fmt::Display for Root {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.node)?;
+        for trivia in self.trailing.iter() {
+            write!(f, "{}", trivia)?;
+        }
+        Ok(())
+    }
+}
+
+impl Node {
+    fn to_bin(&self) -> Bin {
+        match self {
+            Self::Number { value, .. } => Bin::new_uno(Expr::Lit(Literal::Num(value.clone()))),
+            Self::Parenthesized { expr, .. } => expr.to_bin(),
+            Self::Binary { left, op, right } => Bin::new_bin(
+                Expr::BinOp(Box::new(left.to_bin())),
+                Operator {
+                    lexeme: op.text.clone(),
+                    precedence: OPERATORS
+                        .iter()
+                        .find(|(lexeme, _)| *lexeme == op.text)
+                        .map_or(0, |(_, precedence)| *precedence),
+                    assoc: Assoc::Left,
+                    span: op.span,
+                },
+                Expr::BinOp(Box::new(right.to_bin())),
+            ),
+            Self::Let { .. } => unreachable!("let bindings only appear at the root"),
+        }
+    }
+    #[allow(dead_code)]
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            Self::Let {
+                identifier, value, ..
+            } => Expr::Var(Box::new(Var {
+                identifier: identifier.text.clone(),
+                value: value.to_bin(),
+            })),
+            _ => Expr::BinOp(Box::new(self.to_bin())),
+        }
+    }
+}
+
+pub fn take_trivia<'a>() -> impl Parser<Located<&'a str>, Output = Vec<Trivia>> {
+    let trivia = |kind: TriviaKind| {
+        move |(text, span): (Located<&'a str>, Span)| Trivia {
+            kind: kind.clone(),
+            text: text.fragment().to_string(),
+            span,
+        }
+    };
+    many(
+        spanned(take_whitespaces1())
+            .map(trivia(TriviaKind::Whitespace))
+            .or(spanned(recognize(preceded(
+                take_str("//".to_string()),
+                take_predicate(|c: &char| *c != '\n'),
+            )))
+            .map(trivia(TriviaKind::Comment))),
+    )
+}
+fn token<'a, P: Parser<Located<&'a str>>>(
+    parser: P,
+) -> impl Parser<Located<&'a str>, Output = (Token, P::Output)> {
+    let trivia = take_trivia();
+    move |s: Located<&'a str>| {
+        let (start, leading) = trivia.parse(s)?;
+        let (remaining, output) = parser.parse(start)?;
+        let token = Token {
+            leading,
+            text: remaining.consumed_since(start).fragment().to_string(),
+            span: Span::new(start.position(), remaining.position()),
+        };
+        Ok((remaining, (token, output)))
+    }
+}
+fn operator_table<'a>() -> OperatorTable<'a, Located<&'a str>, Node> {
+    OPERATORS
+        .iter()
+        .fold(OperatorTable::new(), |table, &(lexeme, precedence)| {
+            table.infix(
+                precedence,
+                Assoc::Left,
+                token(take_str(lexeme.to_string())).map(|(op, _)| op),
+                |left, op, right| Node::Binary {
+                    left: Box::new(left),
+                    op,
+                    right: Box::new(right),
+                },
+            )
+        })
+}
+fn take_parenthesized<'a, P: Parser<Located<&'a str>, Output = Node>>(
+    expr: P,
+) -> impl Parser<Located<&'a str>, Output = Node> {
+    rule(
+        "parenthesized",
+        context(
+            "parenthesized expression",
+            tuple((token(take_char('(')), expr, token(take_char(')')))),
+        ),
+    )
+    .map(|((open, _), expr, (close, _))| Node::Parenthesized {
+        open,
+        expr: Box::new(expr),
+        close,
+    })
+}
+fn take_operand<'a, P: Parser<Located<&'a str>, Output = Node>>(
+    expr: P,
+) -> impl Parser<Located<&'a str>, Output = Node> {
+    rule(
+        "operand",
+        token(take_number())
+            .map(|(token, value)| Node::Number { token, value })
+            .or(take_parenthesized(expr)),
+    )
+}
+fn take_node<'a>() -> impl Parser<Located<&'a str>, Output = Node> {
+    recursive(|expr| {
+        rule(
+            "expression",
+            expression(take_operand(expr), operator_table()),
+        )
+    })
+}
+fn take_binding<'a>() -> impl Parser<Located<&'a str>, Output = (Token, Token, String, Token, Node)>
+{
+    rule(
+        "let binding",
+        pair(
+            token(keyword("let")),
+            cut(context(
+                "let binding",
+                tuple((token(take_identifier()), token(take_char('=')), take_node())),
+            )),
+        ),
+    )
+    .map(|((keyword, _), ((identifier, name), (equals, _), value))| {
+        (keyword, identifier, name, equals, value)
+    })
+}
+fn take_let<'a>() -> impl Parser<Located<&'a str>, Output = Node> {
+    take_binding().map(|(keyword, identifier, _, equals, value)| Node::Let {
+        keyword,
+        identifier,
+        equals,
+        value: Box::new(value),
+    })
+}
+pub fn take_expr<'a>() -> impl Parser<Located<&'a str>, Output = Bin> {
+    take_node().map(|node| node.to_bin())
+}
+pub fn take_var<'a>() -> impl Parser<Located<&'a str>, Output = (String, Bin)> {
+    take_binding().map(|(_, _, name, _, value)| (name, value.to_bin()))
+}
+#[allow(dead_code)]
+pub fn take_cst<'a>() -> impl Parser<Located<&'a str>, Output = Root> {
+    pair(take_let().or(take_node()), terminated(take_trivia(), eof()))
+        .map(|(node, trailing)| Root { node, trailing })
+}
+
+#[test]
+fn lossless() {
+    use crate::parser::{build_ast, take_program};
+
+    let source = "let x = ( 1 +2)*3 // three\n";
+    let (_, root) = take_cst().parse(Located::new(source)).unwrap();
+    assert_eq!(root.to_string(), source);
+    assert_eq!(
+        root.trailing
+            .iter()
+            .map(|trivia| &trivia.kind)
+            .collect::<Vec<_>>(),
+        vec![
+            &TriviaKind::Whitespace,
+            &TriviaKind::Comment,
+            &TriviaKind::Whitespace
+        ]
+    );
+    let (_, (identifier, value)) = take_var().parse(Located::new(source)).unwrap();
+    assert_eq!(
+        root.node.to_expr(),
+        Expr::Var(Box::new(Var { identifier, value }))
+    );
+
+    let source = "(1 + 2) * 3 - 4 / 2";
+    let (_, root) = take_cst().parse(Located::new(source)).unwrap();
+    assert_eq!(
+        Some(root.node.to_bin()),
+        build_ast()
+            .parse(Located::new(source))
+            .ok()
+            .map(|(_, ast)| ast)
+    );
+    let source = " // leading\n ( 1)\t+2 ";
+    let (_, root) = take_cst().parse(Located::new(source)).unwrap();
+    assert_eq!(root.to_string(), source);
+    assert_eq!(
+        build_ast().parse(Located::new(source)).map(|(_, ast)| ast),
+        Ok(root.node.to_bin())
+    );
+    let (_, (statements, errors)) = take_program()
+        .parse(Located::new("let x = 1; // one\nx // not an operand\n"))
+        .unwrap();
+    assert_eq!((statements.len(), errors.len()), (2, 1));
+    assert!(take_cst().parse(Located::new("letter")).is_err());
+}
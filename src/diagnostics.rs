@@ -0,0 +1,134 @@
+use crate::error::ParserError;
+use crate::input::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    message: String,
+    span: Option<Span>,
+    notes: Vec<String>,
+    help: Vec<String>,
+}
+#[allow(dead_code)]
+impl Diagnostic {
+    pub fn error(message: String) -> Self {
+        Self {
+            message,
+            span: None,
+            notes: vec![],
+            help: vec![],
+        }
+    }
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help.push(help);
+        self
+    }
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("\x1b[{}m{}\x1b[0m", style, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let mut output = format!(
+            "{}: {}\n",
+            paint("1;31", "error"),
+            paint("1", &self.message)
+        );
+        let gutter = match self.span {
+            Some(span) => " ".repeat(span.start.line.to_string().len()),
+            None => String::new(),
+        };
+        if let Some(span) = self.span {
+            let line = source.lines().nth(span.start.line - 1).unwrap_or("");
+            let width = if span.end.line == span.start.line {
+                span.end.column.saturating_sub(span.start.column).max(1)
+            } else {
+                (line.chars().count() + 1)
+                    .saturating_sub(span.start.column)
+                    .max(1)
+            };
+            output.push_str(&format!(
+                "{}{} {}\n",
+                gutter,
+                paint("1;34", "-->"),
+                span.start
+            ));
+            output.push_str(&format!("{} {}\n", gutter, paint("1;34", "|")));
+            output.push_str(&format!(
+                "{} {}\n",
+                paint("1;34", &format!("{} |", span.start.line)),
+                line
+            ));
+            output.push_str(&format!(
+                "{} {} {}{}\n",
+                gutter,
+                paint("1;34", "|"),
+                " ".repeat(span.start.column - 1),
+                paint("1;31", &"^".repeat(width))
+            ));
+        }
+        for note in self.notes.iter() {
+            output.push_str(&format!(
+                "{} {} note: {}\n",
+                gutter,
+                paint("1;34", "="),
+                note
+            ));
+        }
+        for help in self.help.iter() {
+            output.push_str(&format!(
+                "{} {} help: {}\n",
+                gutter,
+                paint("1;34", "="),
+                help
+            ));
+        }
+        output
+    }
+}
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Self {
+        let mut diagnostic = Self::error(error.message());
+        diagnostic.span = error.span();
+        diagnostic.notes = error.notes().to_vec();
+        for context in error.context().iter().skip(1) {
+            diagnostic.notes.push(format!("while parsing {}", context));
+        }
+        diagnostic.help = error.help().map(str::to_string).into_iter().collect();
+        diagnostic
+    }
+}
+
+#[test]
+fn render() {
+    use crate::eval_input;
+
+    let source = "1 + 2u32";
+    let error = eval_input(source).unwrap_err();
+    assert_eq!(
+        Diagnostic::from(&error).render(source, false),
+        "error: Cannot call operator an i32 and an u32 between them
+ --> 1:3
+  |
+1 | 1 + 2u32
+  |   ^
+  = note: the left operand is a `i32` literal
+  = help: write the right operand as `2i32` to make it a `i32`
+"
+    );
+    let error = eval_input("1 * 3 + 2.5").unwrap_err();
+    assert_eq!(error.help(), None);
+    assert_eq!(
+        error.notes(),
+        ["the left operand is the result of `*` at 1:3, which has type `i32`"]
+    );
+}
@@ -1,37 +1,229 @@
+use crate::input::{Input, Position, Span};
+use std::cmp::Ordering;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
-pub struct ParserError {
-    remaining: String,
+pub struct ParserError(Box<ErrorData>);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Recoverable,
+    Fatal,
+    Incomplete(Needed),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Needed {
+    Unknown,
+    Size(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ErrorData {
+    span: Option<Span>,
+    severity: Severity,
     reason: Option<String>,
+    expected: Vec<String>,
+    found: Option<String>,
+    context: Vec<&'static str>,
+    notes: Vec<String>,
+    help: Option<String>,
 }
 #[allow(dead_code)]
 impl ParserError {
-    pub fn new_no_reason(remaining: String) -> Self {
-        Self {
-            remaining: remaining,
-            reason: None,
-        }
+    fn from_parts(span: Option<Span>, reason: Option<String>) -> Self {
+        Self(Box::new(ErrorData {
+            span,
+            severity: Severity::Recoverable,
+            reason,
+            expected: vec![],
+            found: None,
+            context: vec![],
+            notes: vec![],
+            help: None,
+        }))
+    }
+    pub fn new_no_reason<I: Input>(input: I) -> Self {
+        Self::from_parts(Some(Span::empty(input.position())), None)
     }
     pub fn new_no_rem(reason: String) -> Self {
-        let empty = "".to_string();
-        Self {
-            reason: Some(reason),
-            remaining: empty,
-        }
+        Self::from_parts(None, Some(reason))
     }
-    pub fn new(remaining: String, reason: String) -> Self {
-        Self {
-            reason: Some(reason),
-            remaining: remaining,
+    pub fn new<I: Input>(input: I, reason: String) -> Self {
+        Self::from_parts(Some(Span::empty(input.position())), Some(reason))
+    }
+    pub fn expected<I: Input>(input: I, expected: String) -> Self {
+        let mut error = Self::new_no_reason(input);
+        error.0.expected.push(expected);
+        if input.is_empty() {
+            error.0.found = Some("nothing".to_string());
         }
+        error
+    }
+    pub fn incomplete<I: Input>(input: I, needed: Needed) -> Self {
+        let mut error = Self::new(input, "Incomplete input".to_string());
+        error.0.severity = Severity::Incomplete(needed);
+        error
+    }
+    pub fn new_with_span(span: Span, reason: String) -> Self {
+        Self::from_parts(Some(span), Some(reason))
     }
     pub fn empty() -> Self {
-        let empty = "".to_string();
-        Self {
-            reason: None,
-            remaining: empty,
+        Self::from_parts(None, None)
+    }
+    pub fn span(&self) -> Option<Span> {
+        self.0.span
+    }
+    pub fn position(&self) -> Option<Position> {
+        self.0.span.map(|span| span.start)
+    }
+    pub fn reason(&self) -> Option<&str> {
+        self.0.reason.as_deref()
+    }
+    pub fn expected_items(&self) -> &[String] {
+        &self.0.expected
+    }
+    pub fn found(&self) -> Option<&str> {
+        self.0.found.as_deref()
+    }
+    pub fn message(&self) -> String {
+        if let Some(reason) = self.reason() {
+            return reason.to_string();
+        }
+        let expected = match self.0.expected.split_last() {
+            None => return "Syntax error".to_string(),
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        };
+        let message = match self.found() {
+            Some(found) => format!("Expected {}, found {}", expected, found),
+            None => format!("Expected {}", expected),
+        };
+        match self.0.context.first() {
+            Some(context) => format!("{} while parsing {}", message, context),
+            None => message,
         }
     }
-    pub fn remaining(&self) -> String {
-        self.remaining.clone()
+    pub fn severity(&self) -> Severity {
+        self.0.severity
+    }
+    pub fn is_fatal(&self) -> bool {
+        self.0.severity == Severity::Fatal
     }
+    pub fn is_recoverable(&self) -> bool {
+        self.0.severity == Severity::Recoverable
+    }
+    pub fn is_incomplete(&self) -> bool {
+        self.needed().is_some()
+    }
+    pub fn needed(&self) -> Option<Needed> {
+        match self.0.severity {
+            Severity::Incomplete(needed) => Some(needed),
+            _ => None,
+        }
+    }
+    pub fn into_fatal(mut self) -> Self {
+        if self.is_recoverable() {
+            self.0.severity = Severity::Fatal;
+        }
+        self
+    }
+    pub fn context(&self) -> &[&'static str] {
+        &self.0.context
+    }
+    pub fn notes(&self) -> &[String] {
+        &self.0.notes
+    }
+    pub fn help(&self) -> Option<&str> {
+        self.0.help.as_deref()
+    }
+    pub fn with_note(mut self, note: String) -> Self {
+        self.0.notes.push(note);
+        self
+    }
+    pub fn with_help(mut self, help: String) -> Self {
+        self.0.help = Some(help);
+        self
+    }
+    pub fn with_found(mut self, found: String) -> Self {
+        self.0.found = Some(found);
+        self
+    }
+    pub fn with_label<I: Input>(mut self, input: I, label: String) -> Self
+    where
+        I::Item: fmt::Display,
+    {
+        self.0.reason = None;
+        self.0.expected = vec![label];
+        if self.0.found.is_none() {
+            self.0.found = Some(match input.iter_elements().next() {
+                Some(next) => format!("`{}`", next),
+                None => "nothing".to_string(),
+            });
+        }
+        self
+    }
+    pub fn with_context(mut self, context: &'static str) -> Self {
+        self.0.context.push(context);
+        self
+    }
+    pub fn merge(self, other: Self) -> Self {
+        match (self.is_recoverable(), other.is_recoverable()) {
+            (true, false) => return other,
+            (false, true) => return self,
+            _ => {}
+        }
+        let (mut error, other) = match self.position().cmp(&other.position()) {
+            Ordering::Less => return other,
+            Ordering::Greater => return self,
+            Ordering::Equal => (self, other),
+        };
+        let other = *other.0;
+        for expected in other.expected {
+            if !error.0.expected.contains(&expected) {
+                error.0.expected.push(expected);
+            }
+        }
+        if error.0.reason.is_none() {
+            error.0.reason = other.reason;
+        }
+        if error.0.found.is_none() {
+            error.0.found = other.found;
+        }
+        if error.0.context != other.context {
+            error.0.context.clear();
+        }
+        if error.is_recoverable() {
+            error.0.severity = other.severity;
+        }
+        error
+    }
+}
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(position) = self.position() {
+            write!(f, "{}: ", position)?;
+        }
+        write!(f, "{}", self.message())
+    }
+}
+
+#[test]
+fn merge() {
+    use crate::common::{take_char, take_digit, take_str};
+    use crate::input::Located;
+    use crate::parser::Parser;
+
+    let error = take_char('(')
+        .or(take_char('['))
+        .or(take_digit())
+        .parse(Located::new("+"))
+        .unwrap_err();
+    assert_eq!(error.message(), "Expected `(`, `[` or a digit, found `+`");
+    let error = take_str("ab".to_string())
+        .or(take_char('a').then(take_str("c".to_string())))
+        .parse(Located::new("ax"))
+        .unwrap_err();
+    assert_eq!(error.position().map(|position| position.offset), Some(1));
+    assert_eq!(error.message(), "Expected `c`, found `x`");
 }
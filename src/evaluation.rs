@@ -1,5 +1,5 @@
 use crate::error::ParserError;
-use crate::parser::{Bin, Binary, Expr, Literal, Operator};
+use crate::parser::{Bin, Binary, Expr, Literal, Number, Operator};
 
 pub fn eval_ast(bin: Bin) -> Result<Literal, ParserError> {
     match bin {
@@ -8,7 +8,7 @@ pub fn eval_ast(bin: Bin) -> Result<Literal, ParserError> {
         }) => {
             let left = match left {
                 Expr::Lit(lit) => lit,
-                Expr::BinOp(_) => eval_ast(left.to_bin())?,
+                Expr::BinOp(_) => eval_ast(left.into_bin())?,
                 _ => {
                     return Err(ParserError::new_no_rem(format!(
                         "evaluation failed because {:#?} was inside the tree",
@@ -18,7 +18,7 @@ pub fn eval_ast(bin: Bin) -> Result<Literal, ParserError> {
             };
             let right = match right {
                 Expr::Lit(lit) => lit,
-                Expr::BinOp(_) => eval_ast(right.to_bin())?,
+                Expr::BinOp(_) => eval_ast(right.into_bin())?,
                 _ => {
                     return Err(ParserError::new_no_rem(format!(
                         "evaluation failed because {:#?} was inside the tree",
@@ -30,32 +30,62 @@ pub fn eval_ast(bin: Bin) -> Result<Literal, ParserError> {
         }
         Bin::Uno(expr) => match expr {
             Expr::Lit(lit) => Ok(lit),
-            _ => panic!(),
+            _ => Err(ParserError::new_no_rem(format!(
+                "evaluation failed because {:#?} was inside the tree",
+                expr
+            ))),
         },
     }
 }
 
 pub fn eval_bin(left: Literal, op: Operator, right: Literal) -> Result<Literal, ParserError> {
-    match left {
-        Literal::Num(lnum) => match right {
-            Literal::Num(rnum) => match op.lexeme.as_str() {
-                "+" => Ok(Literal::Num(lnum + rnum)),
-                "-" => Ok(Literal::Num(lnum - rnum)),
-                "*" => Ok(Literal::Num(lnum * rnum)),
-                "/" => Ok(Literal::Num(lnum / rnum)),
-                _ => panic!(),
-            },
-        },
+    let (Literal::Num(lnum), Literal::Num(rnum)) = (left, right);
+    if !matches!(op.lexeme.as_str(), "+" | "-" | "*" | "/") {
+        return Err(ParserError::new_with_span(
+            op.span,
+            format!("Unknown operator `{}`", op.lexeme),
+        ));
+    }
+    let divides_by_zero = op.lexeme == "/" && matches!(rnum, Number::I32(0) | Number::U32(0));
+    let number_type = lnum.get_type();
+    match lnum.checked(&op.lexeme, rnum) {
+        Some(number) => Ok(Literal::Num(number)),
+        None if divides_by_zero => Err(ParserError::new_with_span(
+            op.span,
+            "Attempt to divide by zero".to_string(),
+        )),
+        None => Err(ParserError::new_with_span(
+            op.span,
+            format!("Result of `{}` overflows {}", op.lexeme, number_type),
+        )),
     }
 }
 
 #[test]
 fn eval() {
     use crate::eval_input;
-    use crate::parser::Number;
-    assert_eq!(eval_input("1+2"), Ok((Literal::Num(Number::I32(3)))));
+    assert_eq!(eval_input("1+2"), Ok(Literal::Num(Number::I32(3))));
     assert_eq!(
         eval_input("(1+2) * 3 / 4"),
-        Ok((Literal::Num(Number::I32((1 + 2) * 3 / 4))))
+        Ok(Literal::Num(Number::I32((1 + 2) * 3 / 4)))
+    );
+    let failure = |s| {
+        eval_input(s).map_err(|error| (error.span().map(|span| span.start.column), error.message()))
+    };
+    assert_eq!(
+        failure("1 + 1/0"),
+        Err((Some(6), "Attempt to divide by zero".to_string()))
+    );
+    assert_eq!(
+        failure("2147483647 + 1"),
+        Err((Some(12), "Result of `+` overflows i32".to_string()))
+    );
+    assert_eq!(
+        failure("1u32 - 2u32"),
+        Err((Some(6), "Result of `-` overflows u32".to_string()))
+    );
+    assert_eq!(
+        eval_input("1.0 / 0.0"),
+        Ok(Literal::Num(Number::F32(f32::INFINITY)))
     );
 }
@@ -0,0 +1,195 @@
+use std::fmt;
+use std::iter::{Cloned, Enumerate};
+use std::slice::Iter;
+use std::str::{CharIndices, Chars};
+
+pub trait Input: Copy {
+    type Item;
+    type Iter: Iterator<Item = Self::Item>;
+    type Indices: Iterator<Item = (usize, Self::Item)>;
+
+    fn split_at(&self, n: usize) -> (Self, Self);
+    fn iter_elements(&self) -> Self::Iter;
+    fn iter_indices(&self) -> Self::Indices;
+    fn position(&self) -> Position;
+    fn input_len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.input_len() == 0
+    }
+    fn is_partial(&self) -> bool {
+        false
+    }
+    fn consumed_since(&self, start: Self) -> Self {
+        start
+            .split_at(self.position().offset - start.position().offset)
+            .0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+impl Position {
+    pub fn start() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+    fn advance_str(self, consumed: &str) -> Self {
+        let offset = self.offset + consumed.len();
+        match consumed.rfind('\n') {
+            Some(i) => Self {
+                offset,
+                line: self.line + consumed.matches('\n').count(),
+                column: consumed[i + 1..].chars().count() + 1,
+            },
+            None => Self {
+                offset,
+                line: self.line,
+                column: self.column + consumed.chars().count(),
+            },
+        }
+    }
+    fn advance_slice(self, n: usize) -> Self {
+        Self {
+            offset: self.offset + n,
+            line: self.line,
+            column: self.column + n,
+        }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+    pub fn empty(position: Position) -> Self {
+        Self::new(position, position)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Located<T> {
+    fragment: T,
+    position: Position,
+    partial: bool,
+}
+impl<T: Copy> Located<T> {
+    pub fn new(fragment: T) -> Self {
+        Self {
+            fragment,
+            position: Position::start(),
+            partial: false,
+        }
+    }
+    pub fn partial(fragment: T) -> Self {
+        Self {
+            partial: true,
+            ..Self::new(fragment)
+        }
+    }
+    pub fn at(self, position: Position) -> Self {
+        Self { position, ..self }
+    }
+    pub fn fragment(&self) -> T {
+        self.fragment
+    }
+}
+impl<'a> From<&'a str> for Located<&'a str> {
+    fn from(source: &'a str) -> Self {
+        Self::new(source)
+    }
+}
+impl<'a, T> From<&'a [T]> for Located<&'a [T]> {
+    fn from(source: &'a [T]) -> Self {
+        Self::new(source)
+    }
+}
+
+impl<'a> Input for Located<&'a str> {
+    type Item = char;
+    type Iter = Chars<'a>;
+    type Indices = CharIndices<'a>;
+
+    fn split_at(&self, n: usize) -> (Self, Self) {
+        let (consumed, remaining) = self.fragment.split_at(n);
+        (
+            Self {
+                fragment: consumed,
+                ..*self
+            },
+            Self {
+                fragment: remaining,
+                position: self.position.advance_str(consumed),
+                partial: self.partial,
+            },
+        )
+    }
+    fn iter_elements(&self) -> Self::Iter {
+        self.fragment.chars()
+    }
+    fn iter_indices(&self) -> Self::Indices {
+        self.fragment.char_indices()
+    }
+    fn position(&self) -> Position {
+        self.position
+    }
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+    fn input_len(&self) -> usize {
+        self.fragment.len()
+    }
+}
+
+impl<'a, T: Clone> Input for Located<&'a [T]> {
+    type Item = T;
+    type Iter = Cloned<Iter<'a, T>>;
+    type Indices = Enumerate<Cloned<Iter<'a, T>>>;
+
+    fn split_at(&self, n: usize) -> (Self, Self) {
+        let (consumed, remaining) = self.fragment.split_at(n);
+        (
+            Self {
+                fragment: consumed,
+                ..*self
+            },
+            Self {
+                fragment: remaining,
+                position: self.position.advance_slice(n),
+                partial: self.partial,
+            },
+        )
+    }
+    fn iter_elements(&self) -> Self::Iter {
+        self.fragment.iter().cloned()
+    }
+    fn iter_indices(&self) -> Self::Indices {
+        self.fragment.iter().cloned().enumerate()
+    }
+    fn position(&self) -> Position {
+        self.position
+    }
+    fn is_partial(&self) -> bool {
+        self.partial
+    }
+    fn input_len(&self) -> usize {
+        self.fragment.len()
+    }
+}
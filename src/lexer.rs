@@ -0,0 +1,316 @@
+use crate::combinators::{
+    context, cut, delimited, expression, label, peek, preceded, recover, recursive, spanned,
+    terminated, tuple, OperatorTable,
+};
+use crate::common::{keyword, take_char, take_identifier, take_one_of, take_whitespaces};
+use crate::error::ParserError;
+use crate::input::{Input, Located, Position, Span};
+use crate::math::{take_number, OPERATORS};
+use crate::parser::{Assoc, Bin, Expr, Literal, Number, Operator, Parser, Var};
+use std::fmt;
+use std::iter::{Cloned, Enumerate};
+use std::slice::Iter;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(Number),
+    Identifier(String),
+    Let,
+    Operator(String),
+    LParen,
+    RParen,
+    Equals,
+    Semicolon,
+    Eof,
+}
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Number(_) => write!(f, "number"),
+            Self::Identifier(identifier) => write!(f, "identifier `{}`", identifier),
+            Self::Let => write!(f, "`let`"),
+            Self::Operator(lexeme) => write!(f, "`{}`", lexeme),
+            Self::LParen => write!(f, "`(`"),
+            Self::RParen => write!(f, "`)`"),
+            Self::Equals => write!(f, "`=`"),
+            Self::Semicolon => write!(f, "`;`"),
+            Self::Eof => write!(f, "end of input"),
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+fn take_token_kind<'a>() -> impl Parser<Located<&'a str>, Output = TokenKind> {
+    label(
+        "token",
+        take_number()
+            .map(TokenKind::Number)
+            .or(keyword("let").map(|_| TokenKind::Let))
+            .or(take_identifier().map(TokenKind::Identifier))
+            .or(
+                take_one_of(OPERATORS.iter().map(|(lexeme, _)| *lexeme).collect())
+                    .map(|lexeme| TokenKind::Operator(lexeme.to_string())),
+            )
+            .or(take_char('(').map(|_| TokenKind::LParen))
+            .or(take_char(')').map(|_| TokenKind::RParen))
+            .or(take_char('=').map(|_| TokenKind::Equals))
+            .or(take_char(';').map(|_| TokenKind::Semicolon)),
+    )
+}
+pub fn lex(source: &str) -> Result<Vec<Token>, ParserError> {
+    let token = spanned(take_token_kind()).map(|(kind, span)| Token { kind, span });
+    let mut tokens = vec![];
+    let (mut remaining, _) = take_whitespaces().parse(Located::new(source))?;
+    while !remaining.is_empty() {
+        let (rem, token) = token.parse(remaining)?;
+        tokens.push(token);
+        remaining = take_whitespaces().parse(rem)?.0;
+    }
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span::empty(remaining.position()),
+    });
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tokens<'a> {
+    tokens: &'a [Token],
+    offset: usize,
+    end: Position,
+}
+#[allow(dead_code)]
+impl<'a> Tokens<'a> {
+    pub fn new(tokens: &'a [Token]) -> Self {
+        Self {
+            tokens,
+            offset: 0,
+            end: tokens
+                .last()
+                .map_or(Position::start(), |token| token.span.end),
+        }
+    }
+    pub fn tokens(&self) -> &'a [Token] {
+        self.tokens
+    }
+}
+impl<'a> Input for Tokens<'a> {
+    type Item = Token;
+    type Iter = Cloned<Iter<'a, Token>>;
+    type Indices = Enumerate<Cloned<Iter<'a, Token>>>;
+
+    fn split_at(&self, n: usize) -> (Self, Self) {
+        let (consumed, remaining) = self.tokens.split_at(n);
+        (
+            Self {
+                tokens: consumed,
+                ..*self
+            },
+            Self {
+                tokens: remaining,
+                offset: self.offset + n,
+                end: self.end,
+            },
+        )
+    }
+    fn iter_elements(&self) -> Self::Iter {
+        self.tokens.iter().cloned()
+    }
+    fn iter_indices(&self) -> Self::Indices {
+        self.tokens.iter().cloned().enumerate()
+    }
+    fn position(&self) -> Position {
+        let start = self
+            .tokens
+            .first()
+            .map_or(self.end, |token| token.span.start);
+        Position {
+            offset: self.offset,
+            ..start
+        }
+    }
+    fn input_len(&self) -> usize {
+        self.tokens.len()
+    }
+}
+
+fn take_token<'a, O, F: Fn(&Token) -> Option<O>>(
+    expected: &'static str,
+    matches: F,
+) -> impl Parser<Tokens<'a>, Output = O> {
+    move |s: Tokens<'a>| match s.tokens.first() {
+        Some(token) => {
+            match matches(token) {
+                Some(output) => Ok((s.split_at(1).1, output)),
+                None => Err(ParserError::expected(s, expected.to_string())
+                    .with_found(token.kind.to_string())),
+            }
+        }
+        None => Err(ParserError::expected(s, expected.to_string())),
+    }
+}
+fn take_kind<'a>(kind: TokenKind, expected: &'static str) -> impl Parser<Tokens<'a>, Output = ()> {
+    take_token(expected, move |token| (token.kind == kind).then_some(()))
+}
+fn operator_table<'a>() -> OperatorTable<'a, Tokens<'a>, Bin> {
+    OPERATORS
+        .iter()
+        .fold(OperatorTable::new(), |table, &(lexeme, precedence)| {
+            table.infix(
+                precedence,
+                Assoc::Left,
+                take_token("operator", move |token| match &token.kind {
+                    TokenKind::Operator(op) if op == lexeme => Some(Operator {
+                        lexeme: op.clone(),
+                        precedence,
+                        assoc: Assoc::Left,
+                        span: token.span,
+                    }),
+                    _ => None,
+                }),
+                |left, op, right| {
+                    Bin::new_bin(
+                        Expr::BinOp(Box::new(left)),
+                        op,
+                        Expr::BinOp(Box::new(right)),
+                    )
+                },
+            )
+        })
+}
+pub fn take_token_expr<'a>() -> impl Parser<Tokens<'a>, Output = Bin> {
+    recursive(|expr| {
+        let number = take_token("number", |token| match &token.kind {
+            TokenKind::Number(number) => {
+                Some(Bin::new_uno(Expr::Lit(Literal::Num(number.clone()))))
+            }
+            _ => None,
+        });
+        let parenthesized = context(
+            "parenthesized expression",
+            delimited(
+                take_kind(TokenKind::LParen, "`(`"),
+                expr,
+                take_kind(TokenKind::RParen, "`)`"),
+            ),
+        );
+        expression(number.or(parenthesized), operator_table())
+    })
+}
+pub fn take_token_statement<'a>() -> impl Parser<Tokens<'a>, Output = Expr> {
+    let identifier = take_token("identifier", |token| match &token.kind {
+        TokenKind::Identifier(identifier) => Some(identifier.clone()),
+        _ => None,
+    });
+    let binding = preceded(
+        take_kind(TokenKind::Let, "`let`"),
+        cut(context(
+            "let binding",
+            tuple((
+                identifier,
+                take_kind(TokenKind::Equals, "`=`"),
+                take_token_expr(),
+            )),
+        )),
+    )
+    .map(|(identifier, _, value)| Expr::Var(Box::new(Var { identifier, value })));
+    terminated(
+        binding.or(take_token_expr().map(|bin| Expr::BinOp(Box::new(bin)))),
+        take_kind(TokenKind::Semicolon, "`;`").or(peek(take_kind(TokenKind::Eof, "end of input"))),
+    )
+}
+pub fn take_token_program<'a>() -> impl Parser<Tokens<'a>, Output = (Vec<Expr>, Vec<ParserError>)> {
+    |s: Tokens<'a>| {
+        let statement = recover(
+            take_token_statement(),
+            take_kind(TokenKind::Semicolon, "`;`"),
+        );
+        let end = take_kind(TokenKind::Eof, "end of input");
+        let mut remaining = s;
+        let mut statements = vec![];
+        let mut errors = vec![];
+        while !remaining.is_empty() && end.parse(remaining).is_err() {
+            let (rem, result) = statement.parse(remaining)?;
+            match result {
+                Ok(expr) => statements.push(expr),
+                Err(error) => {
+                    statements.push(Expr::Error(Span::new(remaining.position(), rem.position())));
+                    errors.push(error);
+                }
+            }
+            remaining = rem;
+        }
+        Ok((remaining, (statements, errors)))
+    }
+}
+pub fn lex_program(source: &str) -> Result<(Vec<Expr>, Vec<ParserError>), ParserError> {
+    let tokens = lex(source)?;
+    let (_, program) = take_token_program().parse(Tokens::new(&tokens))?;
+    Ok(program)
+}
+
+#[test]
+fn tokens() {
+    use crate::cst::take_var;
+
+    let source = "let letter = (1 +\n  2u32) * 3.5";
+    let tokens = lex(source).unwrap();
+    assert_eq!(
+        tokens.iter().map(|token| &token.kind).collect::<Vec<_>>(),
+        vec![
+            &TokenKind::Let,
+            &TokenKind::Identifier("letter".to_string()),
+            &TokenKind::Equals,
+            &TokenKind::LParen,
+            &TokenKind::Number(Number::I32(1)),
+            &TokenKind::Operator("+".to_string()),
+            &TokenKind::Number(Number::U32(2)),
+            &TokenKind::RParen,
+            &TokenKind::Operator("*".to_string()),
+            &TokenKind::Number(Number::F32(3.5)),
+            &TokenKind::Eof,
+        ]
+    );
+    assert_eq!(tokens[6].span.start.line, 2);
+    let (_, remaining) = Tokens::new(&tokens).split_at(6);
+    let position = remaining.position();
+    assert_eq!((position.offset, position.line, position.column), (6, 2, 3));
+    assert_eq!(remaining.input_len(), 5);
+    let (remaining, statement) = take_token_statement().parse(Tokens::new(&tokens)).unwrap();
+    assert_eq!(remaining.input_len(), 1);
+    let (_, (identifier, value)) = take_var().parse(Located::new(source)).unwrap();
+    assert_eq!(statement, Expr::Var(Box::new(Var { identifier, value })));
+
+    let (statements, errors) = lex_program("letter = 1;\n(1 +\n  );\n2 * 3").unwrap();
+    assert_eq!(statements.len(), 3);
+    assert!(matches!(statements[2], Expr::BinOp(_)));
+    let messages = errors
+        .iter()
+        .map(|error| {
+            let position = error.position().unwrap();
+            (position.line, position.column, error.message())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                1,
+                1,
+                "Expected `let`, number or `(`, found identifier `letter`".to_string()
+            ),
+            (
+                3,
+                3,
+                "Expected number or `(`, found `)` while parsing parenthesized expression"
+                    .to_string()
+            ),
+        ]
+    );
+    let error = lex("1 $ 2").unwrap_err();
+    assert_eq!(error.message(), "Expected token, found `$`");
+}
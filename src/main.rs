@@ -1,18 +1,35 @@
 mod combinators;
 mod common;
+mod cst;
+mod diagnostics;
 mod error;
 mod evaluation;
+mod input;
+mod lexer;
 mod math;
+mod memo;
 mod parser;
+mod profile;
+mod stream;
+mod trace;
 mod typechecking;
 
+use combinators::{cut, terminated};
+use cst::take_var;
+use diagnostics::Diagnostic;
 use error::ParserError;
 use evaluation::eval_ast;
-use parser::{build_ast, take_var, Expr, Literal};
+use input::Located;
+use lexer::lex_program;
+use parser::{build_ast, take_end, take_program, Expr, Literal, Parser};
+use profile::{is_profiling, set_profiling, take_report};
 use std::{
     collections::HashMap,
-    io::{stdin, stdout, Write},
+    env, fs,
+    io::{stdin, stdout, IsTerminal, Write},
+    process,
 };
+use trace::{is_tracing, set_tracing, take_trace};
 use typechecking::type_ast;
 
 struct Environment {
@@ -24,23 +41,160 @@ impl Environment {
         Self { variables }
     }
 }
-fn eval_input<'a>(s: &'a str, variables: &'a mut HashMap<String, Expr>) {
-    let ast = take_var()((s.to_string(), variables)).map_err(|error| {
-        build_ast()(error.remaining()).map(|ast| println!("{:#?}", eval_ast(ast.1)))
-    });
-    println!("{:#?}", variables);
+fn eval_input(s: &str) -> Result<Literal, ParserError> {
+    let (_, ast) = build_ast().parse(Located::new(s))?;
+    eval_ast(type_ast(ast)?)
+}
+fn emit(diagnostic: &Diagnostic, source: &str) {
+    let color = env::var_os("NO_COLOR").is_none() && stdout().is_terminal();
+    print!("{}", diagnostic.render(source, color));
+}
+fn report(error: &ParserError, source: &str) {
+    emit(&Diagnostic::from(error), source);
+}
+fn run_statements(statements: Vec<Expr>, env: &mut Environment, source: &str) {
+    for statement in statements {
+        match statement {
+            Expr::Var(var) => {
+                env.variables
+                    .insert(var.identifier, Expr::BinOp(Box::new(var.value)));
+            }
+            Expr::BinOp(bin) => match type_ast(*bin).and_then(eval_ast) {
+                Ok(literal) => println!("{:#?}", literal),
+                Err(error) => report(&error, source),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+fn run_tokens(s: &str, env: &mut Environment) {
+    match lex_program(s) {
+        Ok((statements, errors)) if errors.is_empty() => run_statements(statements, env, s),
+        Ok((_, errors)) => {
+            for error in errors.iter() {
+                report(error, s);
+            }
+        }
+        Err(error) => report(&error, s),
+    }
+}
+fn run_line(s: &str, env: &mut Environment, tokens: bool) {
+    take_trace();
+    take_report();
+    if tokens {
+        run_tokens(s, env);
+        return;
+    }
+    match terminated(take_var(), cut(take_end())).parse(Located::new(s)) {
+        Ok((_, (identifier, value))) => {
+            env.variables
+                .insert(identifier, Expr::BinOp(Box::new(value)));
+            println!("{:#?}", env.variables);
+        }
+        Err(error) if error.is_fatal() => report(&error, s),
+        Err(_) => match eval_input(s) {
+            Ok(literal) => println!("{:#?}", literal),
+            Err(error) => report(&error, s),
+        },
+    }
+    for line in take_trace() {
+        eprintln!("{}", line);
+    }
+    if is_profiling() {
+        eprint!("{}", take_report());
+    }
+}
+fn needs_more(s: &str) -> bool {
+    let line = || {
+        terminated(take_var(), take_end())
+            .map(|_| ())
+            .or(build_ast().map(|_| ()))
+    };
+    let at_end = |error: &ParserError| error.position().map(|p| p.offset) == Some(s.len());
+    matches!(line().parse(Located::new(s)), Err(error) if at_end(&error))
+        && matches!(line().parse(Located::partial(s)), Err(error) if error.is_incomplete())
+}
+fn run_script(path: &str, env: &mut Environment) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            emit(
+                &Diagnostic::error(format!("Failed to read `{}`: {}", path, error)),
+                "",
+            );
+            process::exit(1);
+        }
+    };
+    let (statements, errors) = match take_program().parse(Located::new(&source)) {
+        Ok((_, program)) => program,
+        Err(error) => (vec![], vec![error]),
+    };
+    for error in errors.iter() {
+        report(error, &source);
+    }
+    if !errors.is_empty() {
+        process::exit(1);
+    }
+    run_statements(statements, env, &source);
 }
 fn main() {
-    let mut variables = HashMap::new();
+    let mut env = Environment::new();
+    if let Some(path) = env::args().nth(1) {
+        run_script(&path, &mut env);
+        return;
+    }
+    let mut buffer = String::new();
+    let mut tokens = false;
     loop {
         let mut input = String::new();
-        print!(">>> ");
+        print!("{}", if buffer.is_empty() { ">>> " } else { "... " });
         stdout().flush().expect("Failed to write line");
-        stdin().read_line(&mut input).expect("Failed to read line");
+        if stdin().read_line(&mut input).expect("Failed to read line") == 0 {
+            break;
+        }
         let input = input.trim();
-        if input == "quit" {
+        if buffer.is_empty() && input == "quit" {
             break;
         }
-        let r = eval_input(input, &mut variables);
+        if buffer.is_empty() && input == "trace" {
+            set_tracing(!is_tracing());
+            continue;
+        }
+        if buffer.is_empty() && input == "profile" {
+            set_profiling(!is_profiling());
+            continue;
+        }
+        if buffer.is_empty() && input == "tokens" {
+            tokens = !tokens;
+            continue;
+        }
+        if !buffer.is_empty() && input.is_empty() {
+            run_line(&buffer, &mut env, tokens);
+            buffer.clear();
+            continue;
+        }
+        if !buffer.is_empty() {
+            buffer.push('\n');
+        }
+        buffer.push_str(input);
+        if !needs_more(&buffer) {
+            run_line(&buffer, &mut env, tokens);
+            buffer.clear();
+        }
     }
 }
+
+#[test]
+fn continuation() {
+    assert!(needs_more("1 +"));
+    assert!(needs_more("let x = (1 +\n  2"));
+    assert!(!needs_more("1 + 2"));
+    assert!(!needs_more("1 + 99999999999"));
+    assert!(!needs_more("let x = 5u"));
+    assert!(!needs_more("let x = 5;"));
+    assert!(terminated(take_var(), cut(take_end()))
+        .parse(Located::new("let x = 5; // five"))
+        .is_ok());
+    assert!(eval_input("1 + 2;").is_ok());
+    assert!(eval_input("1 + 2;;").is_err());
+}
@@ -0,0 +1,200 @@
+use crate::error::ParserError;
+use crate::input::Input;
+use crate::parser::Parser;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+type Entry<O> = Result<(usize, O), ParserError>;
+type Cache = HashMap<(&'static str, usize), Box<dyn Any>>;
+
+thread_local! {
+    static CACHE: RefCell<Option<Cache>> = const { RefCell::new(None) };
+}
+
+fn lookup<O: Clone + 'static>(key: (&'static str, usize)) -> Option<Option<Entry<O>>> {
+    CACHE.with(|cache| {
+        cache.borrow().as_ref().map(|cache| {
+            cache
+                .get(&key)
+                .and_then(|entry| entry.downcast_ref::<Entry<O>>())
+                .cloned()
+        })
+    })
+}
+fn store<O: 'static>(key: (&'static str, usize), entry: Entry<O>) {
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.insert(key, Box::new(entry));
+        }
+    })
+}
+fn restore<I: Input, O>(s: I, entry: Entry<O>) -> Result<(I, O), ParserError> {
+    entry.map(|(consumed, output)| (s.split_at(consumed).1, output))
+}
+fn scoped<T, F: FnOnce() -> T>(f: F) -> T {
+    let outer = CACHE.with(|cache| cache.replace(Some(HashMap::new())));
+    let result = f();
+    CACHE.with(|cache| cache.replace(outer));
+    result
+}
+fn cached_keys() -> HashSet<(&'static str, usize)> {
+    CACHE.with(|cache| {
+        cache
+            .borrow()
+            .as_ref()
+            .map_or(HashSet::new(), |cache| cache.keys().copied().collect())
+    })
+}
+fn evict_since(keys: &HashSet<(&'static str, usize)>, keep: (&'static str, usize)) {
+    CACHE.with(|cache| {
+        if let Some(cache) = cache.borrow_mut().as_mut() {
+            cache.retain(|key, _| *key == keep || keys.contains(key));
+        }
+    })
+}
+fn consumed<I: Input>(s: I, remaining: I) -> usize {
+    remaining.position().offset - s.position().offset
+}
+#[allow(dead_code)]
+pub fn packrat<I: Input, P: Parser<I>>(parser: P) -> impl Parser<I, Output = P::Output> {
+    move |s: I| scoped(|| parser.parse(s))
+}
+#[allow(dead_code)]
+pub fn memo<I: Input, P: Parser<I>>(
+    rule: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output>
+where
+    P::Output: Clone + 'static,
+{
+    move |s: I| {
+        let key = (rule, s.position().offset);
+        match lookup(key) {
+            None => return parser.parse(s),
+            Some(Some(entry)) => return restore(s, entry),
+            Some(None) => {}
+        }
+        let result = parser.parse(s);
+        let entry = match &result {
+            Ok((remaining, output)) => Ok((consumed(s, *remaining), output.clone())),
+            Err(error) => Err(error.clone()),
+        };
+        store(key, entry);
+        result
+    }
+}
+fn grow<I: Input, P: Parser<I>>(
+    key: (&'static str, usize),
+    parser: &P,
+    s: I,
+) -> Result<(I, P::Output), ParserError>
+where
+    P::Output: Clone + 'static,
+{
+    store::<P::Output>(key, Err(ParserError::new_no_reason(s)));
+    let round = || {
+        let keys = cached_keys();
+        let result = parser.parse(s);
+        evict_since(&keys, key);
+        result
+    };
+    let mut best = match round() {
+        Ok((remaining, output)) => (consumed(s, remaining), output),
+        Err(error) => {
+            store::<P::Output>(key, Err(error.clone()));
+            return Err(error);
+        }
+    };
+    loop {
+        store(key, Ok(best.clone()));
+        match round() {
+            Ok((remaining, output)) if consumed(s, remaining) > best.0 => {
+                best = (consumed(s, remaining), output)
+            }
+            Err(error) if !error.is_recoverable() => return Err(error),
+            _ => break,
+        }
+    }
+    restore(s, Ok(best))
+}
+#[allow(dead_code)]
+pub fn left_recursive<I: Input, P: Parser<I>>(
+    rule: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output>
+where
+    P::Output: Clone + 'static,
+{
+    move |s: I| {
+        let key = (rule, s.position().offset);
+        match lookup(key) {
+            None => scoped(|| grow(key, &parser, s)),
+            Some(Some(entry)) => restore(s, entry),
+            Some(None) => grow(key, &parser, s),
+        }
+    }
+}
+
+#[test]
+fn packrat_parsing() {
+    use crate::combinators::{many1, terminated};
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let digits = || {
+        memo(
+            "digits",
+            many1(take_digit()).map(|digits| {
+                calls.set(calls.get() + 1);
+                digits.len()
+            }),
+        )
+    };
+    let grammar = terminated(digits(), take_char('a'))
+        .or(terminated(digits(), take_char('b')))
+        .or(terminated(digits(), take_char('c')));
+    assert_eq!(grammar.parse(Located::new("123c")).map(|(_, n)| n), Ok(3));
+    assert_eq!(calls.get(), 3);
+    calls.set(0);
+    let (remaining, n) = packrat(grammar).parse(Located::new("123c")).unwrap();
+    assert_eq!((remaining.fragment(), n), ("", 3));
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn left_recursion() {
+    use crate::combinators::{recursive, tuple};
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let digit = || take_digit().map(|digit| digit.to_digit(10).unwrap() as i32);
+    let expr = recursive(|expr| {
+        left_recursive(
+            "expr",
+            tuple((expr, take_char('-'), digit()))
+                .map(|(left, _, right)| left - right)
+                .or(digit()),
+        )
+    });
+    let (remaining, value) = expr.parse(Located::new("8-4-2)")).unwrap();
+    assert_eq!((remaining.fragment(), value), (")", 2));
+    assert_eq!(expr.parse(Located::new("7")).map(|(_, value)| value), Ok(7));
+    let error = expr.parse(Located::new("-")).unwrap_err();
+    assert_eq!(error.message(), "Expected a digit, found `-`");
+
+    let expr = packrat(recursive(|expr| {
+        left_recursive(
+            "expr",
+            memo(
+                "difference",
+                tuple((expr, take_char('-'), digit())).map(|(left, _, right)| left - right),
+            )
+            .or(digit()),
+        )
+    }));
+    let (remaining, value) = expr.parse(Located::new("8-4-2")).unwrap();
+    assert_eq!((remaining.fragment(), value), ("", 2));
+}
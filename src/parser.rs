@@ -1,13 +1,99 @@
-use crate::combinators::{many, many1};
-use crate::common::{
-    take_char, take_identifier, take_predicate1, take_str, take_whitespaces, take_whitespaces1,
-};
+use crate::combinators::{eof, opt, preceded, recover, terminated};
+use crate::common::take_char;
+use crate::cst::{take_expr, take_trivia, take_var};
 use crate::error::ParserError;
-use crate::math::{into_ast, take_number, take_operator};
-use std::collections::HashMap;
-use std::ops::{Add, Div, Mul, Sub};
+use crate::input::{Input, Located, Span};
+use std::fmt;
 
-pub type Parser<T, X> = Box<dyn Fn(X) -> Result<(String, T), ParserError>>;
+pub trait Parser<I> {
+    type Output;
+
+    fn parse(&self, input: I) -> Result<(I, Self::Output), ParserError>;
+
+    fn map<F, U>(self, f: F) -> impl Parser<I, Output = U>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> U,
+    {
+        move |input: I| {
+            self.parse(input)
+                .map(|(remaining, output)| (remaining, f(output)))
+        }
+    }
+    #[allow(dead_code)]
+    fn and_then<F, P>(self, f: F) -> impl Parser<I, Output = P::Output>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> P,
+        P: Parser<I>,
+    {
+        move |input: I| {
+            let (remaining, output) = self.parse(input)?;
+            f(output).parse(remaining)
+        }
+    }
+    fn or<P>(self, other: P) -> impl Parser<I, Output = Self::Output>
+    where
+        Self: Sized,
+        I: Clone,
+        P: Parser<I, Output = Self::Output>,
+    {
+        move |input: I| match self.parse(input.clone()) {
+            Err(error) if error.is_recoverable() => {
+                other.parse(input).map_err(|other| error.merge(other))
+            }
+            result => result,
+        }
+    }
+    fn then<P>(self, other: P) -> impl Parser<I, Output = P::Output>
+    where
+        Self: Sized,
+        P: Parser<I>,
+    {
+        move |input: I| {
+            let (remaining, _) = self.parse(input)?;
+            other.parse(remaining)
+        }
+    }
+    fn skip<P>(self, other: P) -> impl Parser<I, Output = Self::Output>
+    where
+        Self: Sized,
+        P: Parser<I>,
+    {
+        move |input: I| {
+            let (remaining, output) = self.parse(input)?;
+            let (remaining, _) = other.parse(remaining)?;
+            Ok((remaining, output))
+        }
+    }
+    #[allow(dead_code)]
+    fn boxed<'a>(self) -> BoxedParser<'a, I, Self::Output>
+    where
+        Self: Sized + 'a,
+    {
+        Box::new(self)
+    }
+}
+impl<I, O, F> Parser<I> for F
+where
+    F: Fn(I) -> Result<(I, O), ParserError>,
+{
+    type Output = O;
+
+    fn parse(&self, input: I) -> Result<(I, O), ParserError> {
+        self(input)
+    }
+}
+
+pub type BoxedParser<'a, I, O> = Box<dyn Parser<I, Output = O> + 'a>;
+
+impl<'a, I, O> Parser<I> for BoxedParser<'a, I, O> {
+    type Output = O;
+
+    fn parse(&self, input: I) -> Result<(I, O), ParserError> {
+        (**self).parse(input)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Number {
@@ -23,84 +109,33 @@ impl Number {
             Self::F32(_) => Type::F32,
         }
     }
-}
-impl Add for Number {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self {
-        match self {
-            Self::I32(lnum) => match other {
-                Self::I32(rnum) => return Self::I32(lnum + rnum),
-                _ => unreachable!(),
-            },
-            Self::U32(lnum) => match other {
-                Self::U32(rnum) => return Self::U32(lnum + rnum),
-                _ => unreachable!(),
-            },
-            Self::F32(lnum) => match other {
-                Self::F32(rnum) => return Self::F32(lnum + rnum),
-                _ => unreachable!(),
-            },
-        }
-    }
-}
-impl Sub for Number {
-    type Output = Self;
-
-    fn sub(self, other: Self) -> Self {
-        match self {
-            Self::I32(lnum) => match other {
-                Self::I32(rnum) => return Self::I32(lnum - rnum),
-                _ => unreachable!(),
-            },
-            Self::U32(lnum) => match other {
-                Self::U32(rnum) => return Self::U32(lnum - rnum),
-                _ => unreachable!(),
-            },
-            Self::F32(lnum) => match other {
-                Self::F32(rnum) => return Self::F32(lnum - rnum),
-                _ => unreachable!(),
-            },
-        }
-    }
-}
-impl Mul for Number {
-    type Output = Self;
-
-    fn mul(self, other: Self) -> Self {
-        match self {
-            Self::I32(lnum) => match other {
-                Self::I32(rnum) => return Self::I32(lnum * rnum),
-                _ => unreachable!(),
-            },
-            Self::U32(lnum) => match other {
-                Self::U32(rnum) => return Self::U32(lnum * rnum),
-                _ => unreachable!(),
-            },
-            Self::F32(lnum) => match other {
-                Self::F32(rnum) => return Self::F32(lnum * rnum),
-                _ => unreachable!(),
-            },
-        }
-    }
-}
-impl Div for Number {
-    type Output = Self;
-
-    fn div(self, other: Self) -> Self {
-        match self {
-            Self::I32(lnum) => match other {
-                Self::I32(rnum) => return Self::I32(lnum / rnum),
-                _ => unreachable!(),
-            },
-            Self::U32(lnum) => match other {
-                Self::U32(rnum) => return Self::U32(lnum / rnum),
-                _ => unreachable!(),
-            },
-            Self::F32(lnum) => match other {
-                Self::F32(rnum) => return Self::F32(lnum / rnum),
-                _ => unreachable!(),
-            },
+    pub fn checked(self, lexeme: &str, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Self::I32(lnum), Self::I32(rnum)) => match lexeme {
+                "+" => lnum.checked_add(rnum),
+                "-" => lnum.checked_sub(rnum),
+                "*" => lnum.checked_mul(rnum),
+                "/" => lnum.checked_div(rnum),
+                _ => None,
+            }
+            .map(Self::I32),
+            (Self::U32(lnum), Self::U32(rnum)) => match lexeme {
+                "+" => lnum.checked_add(rnum),
+                "-" => lnum.checked_sub(rnum),
+                "*" => lnum.checked_mul(rnum),
+                "/" => lnum.checked_div(rnum),
+                _ => None,
+            }
+            .map(Self::U32),
+            (Self::F32(lnum), Self::F32(rnum)) => match lexeme {
+                "+" => Some(lnum + rnum),
+                "-" => Some(lnum - rnum),
+                "*" => Some(lnum * rnum),
+                "/" => Some(lnum / rnum),
+                _ => None,
+            }
+            .map(Self::F32),
+            _ => None,
         }
     }
 }
@@ -111,6 +146,15 @@ pub enum Type {
     I32,
     F32,
 }
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::U32 => write!(f, "u32"),
+            Self::I32 => write!(f, "i32"),
+            Self::F32 => write!(f, "f32"),
+        }
+    }
+}
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     Num(Number),
@@ -123,40 +167,26 @@ impl Literal {
     }
 }
 #[derive(Debug, Clone, PartialEq)]
-pub enum OpTerm {
-    Op(Operator),
-    OpTerm(Expr),
-}
-impl OpTerm {
-    pub fn new_op(lexeme: String, precedence: i32, assoc: Assoc) -> Self {
-        Self::Op(Operator {
-            lexeme,
-            precedence,
-            assoc,
-        })
-    }
-}
-#[derive(Debug, Clone, PartialEq)]
 pub struct Var {
-    identifier: String,
-    value: Bin,
+    pub identifier: String,
+    pub value: Bin,
 }
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum Expr {
     Lit(Literal),
     Var(Box<Var>),
-    Operation(Vec<OpTerm>),
     BinOp(Box<Bin>),
+    Error(Span),
 }
 impl Expr {
     pub fn get_type(&self) -> Type {
         match self {
             Self::Lit(literal) => literal.get_type(),
-            _ => unreachable!(), // In the typed ast, there is normally no Operation variant
+            _ => unreachable!(), // In the typed ast, only literals are left in Bin::Uno
         }
     }
-    pub fn to_bin(self) -> Bin {
+    pub fn into_bin(self) -> Bin {
         match self {
             Self::BinOp(bin) => *bin,
             _ => panic!("Can't call `to_bin` on an expr which is not a Expr::BinOp"),
@@ -168,15 +198,7 @@ pub struct Operator {
     pub lexeme: String,
     pub precedence: i32,
     pub assoc: Assoc,
-}
-impl Operator {
-    pub fn is_left_assoc(&self) -> bool {
-        if let Assoc::Left = self.assoc {
-            true
-        } else {
-            false
-        }
-    }
+    pub span: Span,
 }
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
@@ -226,75 +248,78 @@ impl Bin {
         }
     }
 }
-pub fn take_parenthesized() -> Parser<Vec<OpTerm>, ParserError> {
-    Box::new(|s| {
-        take_char('(')(s.remaining()).and_then(|(remaining, _)| {
-            let (remaining, expr) = many1(take_expr())(remaining)?;
-            let (remaining, _) = take_char(')')(remaining)?;
-            Ok((
-                remaining,
-                vec![OpTerm::OpTerm(Expr::Operation(
-                    expr.into_iter().nth(0).unwrap(),
-                ))],
-            ))
-        })
-    })
+pub fn take_end<'a>() -> impl Parser<Located<&'a str>, Output = ()> {
+    preceded(
+        take_trivia(),
+        opt(take_char(';')).then(preceded(take_trivia(), eof())),
+    )
 }
-pub fn take_expr() -> Parser<Vec<OpTerm>, String> {
-    Box::new(move |s| {
-        let (remaining, mut expr) = take_number()(s)
-            .and_then(|(remaining, expr)| {
-                Ok((
-                    remaining,
-                    vec![OpTerm::OpTerm(Expr::Lit(Literal::Num(expr)))],
-                ))
-            })
-            .or_else(take_parenthesized())?;
-        let (remaining, _) = take_whitespaces()(remaining)?;
-        let (remaining, values) = many(Box::new(|s| {
-            let mut temp_expr = vec![];
-            take_operator()(s)
-                .and_then(|(remaining, op)| {
-                    temp_expr.push(op);
-                    take_whitespaces()(remaining)
-                })
-                .and_then(|(remaining, _)| {
-                    let (remaining, num) = take_number()(remaining)?;
-                    temp_expr.push(OpTerm::OpTerm(Expr::Lit(Literal::Num(num))));
-                    let (remaining, _) = take_whitespaces()(remaining)?;
-                    Ok((remaining, temp_expr))
-                })
-                .or_else(take_parenthesized())
-        }))(remaining)?;
-        for i in values {
-            for j in i {
-                expr.push(j);
-            }
+pub fn build_ast<'a>() -> impl Parser<Located<&'a str>, Output = Bin> {
+    terminated(take_expr(), take_end())
+}
+fn take_terminator<'a>() -> impl Parser<Located<&'a str>, Output = ()> {
+    let trivia = take_trivia();
+    move |s: Located<&'a str>| {
+        let (s, _) = trivia.parse(s)?;
+        if s.is_empty() && !s.is_partial() {
+            return Ok((s, ()));
         }
-        Ok((remaining, expr))
-    })
+        take_char(';').map(|_| ()).parse(s)
+    }
+}
+pub fn take_statement<'a>() -> impl Parser<Located<&'a str>, Output = Expr> {
+    take_var()
+        .map(|(identifier, value)| Expr::Var(Box::new(Var { identifier, value })))
+        .or(take_expr().map(|bin| Expr::BinOp(Box::new(bin))))
+        .skip(take_terminator())
 }
-pub fn take_var<'a>() -> Parser<(), (String, &'a mut HashMap<String, Expr>)> {
-    Box::new(move |(s, variables)| {
-        let mut identifier = String::new();
-        let (remaining, expr) = take_str("let".to_string())(s)
-            .and_then(|(remaining, _)| take_whitespaces1()(remaining))
-            .and_then(|(remaining, _)| take_identifier()(remaining))
-            .map(|(remaining, ident)| {
-                identifier = ident;
-                ((remaining, ()))
-            })
-            .and_then(|(remaining, _)| take_whitespaces()(remaining))
-            .and_then(|(remaining, _)| take_char('=')(remaining))
-            .and_then(|(remaining, _)| take_whitespaces()(remaining))
-            .and_then(|(remaining, _)| take_predicate1(Box::new(|s| s != ';'))(remaining))?;
-        let expr = Expr::BinOp(Box::new(build_ast()(expr)?.1));
-        variables.insert(identifier, expr);
-        Ok((remaining, ()))
-    })
+pub fn take_program<'a>() -> impl Parser<Located<&'a str>, Output = (Vec<Expr>, Vec<ParserError>)> {
+    |s: Located<&'a str>| {
+        let statement = recover(take_statement(), take_char(';'));
+        let trivia = take_trivia();
+        let (mut remaining, _) = trivia.parse(s)?;
+        let mut statements = vec![];
+        let mut errors = vec![];
+        while !remaining.is_empty() {
+            let (rem, result) = statement.parse(remaining)?;
+            match result {
+                Ok(expr) => statements.push(expr),
+                Err(error) => {
+                    statements.push(Expr::Error(Span::new(remaining.position(), rem.position())));
+                    errors.push(error);
+                }
+            }
+            remaining = trivia.parse(rem)?.0;
+        }
+        Ok((remaining, (statements, errors)))
+    }
 }
-pub fn build_ast() -> Parser<Bin, String> {
-    Box::new(|s| {
-        take_expr()(s).and_then(|(remaining, tokens)| Ok((remaining, into_ast()(tokens)?.1)))
-    })
+
+#[test]
+fn recovery() {
+    let source = "let x = 1;\n1 + ;\n(2 * 3;\n4 / 2";
+    let (remaining, (statements, errors)) = take_program().parse(Located::new(source)).unwrap();
+    assert!(remaining.is_empty());
+    assert_eq!(statements.len(), 4);
+    assert!(matches!(statements[0], Expr::Var(_)));
+    assert!(matches!(statements[1], Expr::Error(_)));
+    assert!(matches!(statements[2], Expr::Error(_)));
+    assert!(matches!(statements[3], Expr::BinOp(_)));
+    let messages = errors
+        .iter()
+        .map(|error| (error.position().unwrap().line, error.message()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            (2, "Expected number or `(`, found `;`".to_string()),
+            (
+                3,
+                "Expected `)`, found `;` while parsing parenthesized expression".to_string()
+            ),
+        ]
+    );
+    let (_, (statements, errors)) = take_program().parse(Located::new("1\n2;\n3;")).unwrap();
+    assert_eq!((statements.len(), errors.len()), (2, 1));
+    assert!(matches!(statements[1], Expr::BinOp(_)));
 }
@@ -0,0 +1,157 @@
+use crate::input::Input;
+use crate::parser::Parser;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RuleStats {
+    pub calls: usize,
+    pub backtracks: usize,
+    pub time: Duration,
+}
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report(Vec<(&'static str, RuleStats)>);
+
+#[allow(dead_code)]
+impl Report {
+    pub fn rules(&self) -> &[(&'static str, RuleStats)] {
+        &self.0
+    }
+    pub fn get(&self, name: &str) -> Option<RuleStats> {
+        self.0
+            .iter()
+            .find(|(rule, _)| *rule == name)
+            .map(|(_, stats)| *stats)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.0.iter().map(|(rule, _)| rule.len()).max().unwrap_or(0);
+        writeln!(
+            f,
+            "{:<width$}  {:>8}  {:>10}  {:>12}",
+            "rule",
+            "calls",
+            "backtracks",
+            "time",
+            width = width
+        )?;
+        for (rule, stats) in self.0.iter() {
+            writeln!(
+                f,
+                "{:<width$}  {:>8}  {:>10}  {:>12}",
+                rule,
+                stats.calls,
+                stats.backtracks,
+                format!("{:?}", stats.time),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Profile {
+    stats: HashMap<&'static str, RuleStats>,
+    active: HashMap<&'static str, usize>,
+}
+
+thread_local! {
+    static PROFILE: RefCell<Option<Profile>> = const { RefCell::new(None) };
+}
+
+pub fn set_profiling(enabled: bool) {
+    PROFILE.with(|profile| *profile.borrow_mut() = enabled.then(Profile::default))
+}
+pub fn is_profiling() -> bool {
+    PROFILE.with(|profile| profile.borrow().is_some())
+}
+pub fn take_report() -> Report {
+    let mut rules = PROFILE.with(|profile| {
+        profile
+            .borrow_mut()
+            .as_mut()
+            .map_or(vec![], |profile| profile.stats.drain().collect::<Vec<_>>())
+    });
+    rules.sort_by(|(a, a_stats), (b, b_stats)| b_stats.time.cmp(&a_stats.time).then(a.cmp(b)));
+    Report(rules)
+}
+pub fn profile<I: Input, P: Parser<I>>(
+    name: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output> {
+    move |s: I| {
+        if !is_profiling() {
+            return parser.parse(s);
+        }
+        let outermost = PROFILE.with(|profile| {
+            profile.borrow_mut().as_mut().is_some_and(|profile| {
+                let depth = profile.active.entry(name).or_default();
+                *depth += 1;
+                *depth == 1
+            })
+        });
+        let start = Instant::now();
+        let result = parser.parse(s);
+        let elapsed = start.elapsed();
+        PROFILE.with(|profile| {
+            if let Some(profile) = profile.borrow_mut().as_mut() {
+                if let Some(depth) = profile.active.get_mut(name) {
+                    *depth -= 1;
+                }
+                let stats = profile.stats.entry(name).or_default();
+                stats.calls += 1;
+                if outermost {
+                    stats.time += elapsed;
+                }
+                if matches!(&result, Err(error) if error.is_recoverable()) {
+                    stats.backtracks += 1;
+                }
+            }
+        });
+        result
+    }
+}
+
+#[test]
+fn profiling() {
+    use crate::combinators::{delimited, many, recursive};
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let digit = || profile("digit", take_digit());
+    let list = profile("list", many(digit().skip(take_char(','))));
+    assert!(list.parse(Located::new("1,2,3,")).is_ok());
+    assert!(take_report().is_empty());
+    set_profiling(true);
+    assert!(list.parse(Located::new("1,2,3,")).is_ok());
+    let report = take_report();
+    assert_eq!(report.rules().len(), 2);
+    let digits = report.get("digit").unwrap();
+    assert_eq!((digits.calls, digits.backtracks), (4, 1));
+    let lists = report.get("list").unwrap();
+    assert_eq!(lists.calls, 1);
+    assert!(lists.time >= digits.time);
+    assert!(report.to_string().starts_with("rule   "));
+    assert!(take_report().is_empty());
+
+    let nested = recursive(|nested| {
+        profile(
+            "nested",
+            delimited(take_char('('), nested, take_char(')')).or(take_digit()),
+        )
+    });
+    let start = Instant::now();
+    assert!(nested.parse(Located::new("((((((1))))))")).is_ok());
+    let elapsed = start.elapsed();
+    let nesting = take_report().get("nested").unwrap();
+    assert_eq!(nesting.calls, 7);
+    assert!(nesting.time <= elapsed);
+    set_profiling(false);
+}
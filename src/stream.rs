@@ -0,0 +1,156 @@
+use crate::error::ParserError;
+use crate::input::{Input, Located, Position};
+use std::io::{ErrorKind, Read};
+use std::str;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+pub struct Stream<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    position: Position,
+    exhausted: bool,
+    failed: bool,
+}
+#[allow(dead_code)]
+impl<R: Read> Stream<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![],
+            position: Position::start(),
+            exhausted: false,
+            failed: false,
+        }
+    }
+    pub fn position(&self) -> Position {
+        self.position
+    }
+    fn refill(&mut self) -> Result<(), ParserError> {
+        let start = self.buffer.len();
+        self.buffer.resize(start + CHUNK_SIZE, 0);
+        let read = self.reader.read(&mut self.buffer[start..]);
+        self.buffer
+            .truncate(start + read.as_ref().map_or(0, |read| *read));
+        match read {
+            Ok(0) => self.exhausted = true,
+            Ok(_) => {}
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => {
+                return Err(ParserError::new_no_rem(format!(
+                    "Failed to read input: {}",
+                    error
+                )))
+            }
+        }
+        Ok(())
+    }
+    pub fn parse_next<O, F>(&mut self, parse: F) -> Option<Result<O, ParserError>>
+    where
+        F: Fn(Located<&str>) -> Result<(Located<&str>, O), ParserError>,
+    {
+        if self.failed {
+            return None;
+        }
+        let result = self.parse_buffered(parse);
+        self.failed = matches!(result, Some(Err(_)));
+        result
+    }
+    fn parse_buffered<O, F>(&mut self, parse: F) -> Option<Result<O, ParserError>>
+    where
+        F: Fn(Located<&str>) -> Result<(Located<&str>, O), ParserError>,
+    {
+        loop {
+            let text = match str::from_utf8(&self.buffer) {
+                Ok(text) => text,
+                Err(error) if error.error_len().is_none() && !self.exhausted => {
+                    str::from_utf8(&self.buffer[..error.valid_up_to()]).unwrap()
+                }
+                Err(_) => {
+                    return Some(Err(ParserError::new_no_rem(
+                        "Input is not valid UTF-8".to_string(),
+                    )))
+                }
+            };
+            if self.exhausted && text.is_empty() {
+                return None;
+            }
+            let input = if self.exhausted {
+                Located::new(text)
+            } else {
+                Located::partial(text)
+            };
+            match parse(input.at(self.position)) {
+                Ok((remaining, output)) => {
+                    let position = remaining.position();
+                    self.buffer.drain(..position.offset - self.position.offset);
+                    self.position = position;
+                    return Some(Ok(output));
+                }
+                Err(error) if error.is_incomplete() && !self.exhausted => {
+                    if let Err(error) = self.refill() {
+                        return Some(Err(error));
+                    }
+                }
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[test]
+fn streaming() {
+    use crate::combinators::delimited;
+    use crate::common::{take_char, take_predicate1, take_whitespaces};
+    use crate::parser::{take_statement, Expr, Parser};
+
+    struct Chunked<'a>(&'a [u8]);
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let mut stream = Stream::new(Chunked("héllo;wörld;".as_bytes()));
+    let mut words = vec![];
+    while let Some(result) = stream.parse_next(|s| {
+        take_predicate1(|c: &char| *c != ';')
+            .skip(take_char(';'))
+            .map(|word: Located<&str>| word.fragment().to_string())
+            .parse(s)
+    }) {
+        words.push(result.unwrap());
+    }
+    assert_eq!(words, vec!["héllo", "wörld"]);
+
+    let mut stream = Stream::new(Chunked(b"let x = 1;\n(1 +\n  2) * 3;\n4 / 2\n"));
+    let mut statements = vec![];
+    while let Some(result) = stream.parse_next(|s| {
+        delimited(take_whitespaces(), take_statement(), take_whitespaces()).parse(s)
+    }) {
+        statements.push(result.unwrap());
+    }
+    assert_eq!(statements.len(), 3);
+    assert!(matches!(statements[0], Expr::Var(_)));
+    assert!(matches!(statements[2], Expr::BinOp(_)));
+    assert_eq!(stream.position().line, 5);
+
+    let mut stream = Stream::new(Chunked(b"1;\n+;\n2;"));
+    let mut results = vec![];
+    while let Some(result) = stream.parse_next(|s| {
+        delimited(take_whitespaces(), take_statement(), take_whitespaces()).parse(s)
+    }) {
+        results.push(result.is_ok());
+    }
+    assert_eq!(results, vec![true, false]);
+
+    let mut stream = Stream::new(Chunked(b"1 + 2\xe2\x82"));
+    let error = stream
+        .parse_next(|s| take_statement().parse(s))
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(error.message(), "Input is not valid UTF-8");
+}
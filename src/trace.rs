@@ -0,0 +1,105 @@
+use crate::input::Input;
+use crate::parser::Parser;
+use std::cell::RefCell;
+use std::fmt::Display;
+
+struct Trace {
+    depth: usize,
+    lines: Vec<String>,
+}
+
+thread_local! {
+    static TRACE: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+pub fn set_tracing(enabled: bool) {
+    TRACE.with(|trace| {
+        *trace.borrow_mut() = if enabled {
+            Some(Trace {
+                depth: 0,
+                lines: vec![],
+            })
+        } else {
+            None
+        }
+    })
+}
+pub fn is_tracing() -> bool {
+    TRACE.with(|trace| trace.borrow().is_some())
+}
+pub fn take_trace() -> Vec<String> {
+    TRACE.with(|trace| {
+        trace
+            .borrow_mut()
+            .as_mut()
+            .map_or(vec![], |trace| trace.lines.drain(..).collect())
+    })
+}
+fn log(line: String, depth_change: isize) {
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            if depth_change < 0 {
+                trace.depth -= 1;
+            }
+            trace
+                .lines
+                .push(format!("{}{}", "  ".repeat(trace.depth), line));
+            if depth_change > 0 {
+                trace.depth += 1;
+            }
+        }
+    })
+}
+pub fn trace<I: Input, P: Parser<I>>(
+    name: &'static str,
+    parser: P,
+) -> impl Parser<I, Output = P::Output>
+where
+    I::Item: Display,
+{
+    move |s: I| {
+        if !is_tracing() {
+            return parser.parse(s);
+        }
+        log(format!("{} at {}", name, s.position()), 1);
+        let result = parser.parse(s);
+        match &result {
+            Ok((remaining, _)) => {
+                let consumed = remaining
+                    .consumed_since(s)
+                    .iter_elements()
+                    .map(|item| item.to_string())
+                    .collect::<String>();
+                log(format!("{} matched {:?}", name, consumed), -1)
+            }
+            Err(error) => log(format!("{} failed: {}", name, error), -1),
+        }
+        result
+    }
+}
+
+#[test]
+fn tracing() {
+    use crate::common::{take_char, take_digit};
+    use crate::input::Located;
+
+    let digit = || trace("digit", take_digit());
+    let pair = trace("pair", digit().skip(take_char(',')).then(digit()));
+    assert!(pair.parse(Located::new("1,2")).is_ok());
+    assert!(take_trace().is_empty());
+    set_tracing(true);
+    assert!(pair.parse(Located::new("1,x")).is_err());
+    assert_eq!(
+        take_trace(),
+        vec![
+            "pair at 1:1",
+            "  digit at 1:1",
+            "  digit matched \"1\"",
+            "  digit at 1:3",
+            "  digit failed: 1:3: Expected a digit, found `x`",
+            "pair failed: 1:3: Expected a digit, found `x`",
+        ]
+    );
+    set_tracing(false);
+    assert!(!is_tracing());
+}
@@ -1,22 +1,22 @@
 use crate::error::ParserError;
-use crate::parser::{Bin, Binary, Expr, Operator, Parser, Type};
-use std::ops::DerefMut;
+use crate::parser::{Bin, Binary, Expr, Literal, Number, Operator, Type};
+use std::convert::TryFrom;
 
 impl Bin {
-    pub fn into_typed(&mut self) -> Result<Bin, ParserError> {
+    pub fn to_typed(&self) -> Result<Bin, ParserError> {
         match self {
             Bin::Bin(Binary {
                 left, right, op, ..
             }) => {
                 let left = match left {
-                    Expr::BinOp(bin) => Box::new(bin.deref_mut().into_typed()?),
+                    Expr::BinOp(bin) => Box::new(bin.to_typed()?),
                     _ => unreachable!(),
                 };
                 let right = match right {
-                    Expr::BinOp(bin) => Box::new(bin.deref_mut().into_typed()?),
+                    Expr::BinOp(bin) => Box::new(bin.to_typed()?),
                     _ => unreachable!(),
                 };
-                let expr_type = binary_type(left.get_type(), &op, right.get_type())?;
+                let expr_type = binary_type(&left, op, &right)?;
                 Ok(Bin::new_bin_typed(
                     Expr::BinOp(left),
                     op.clone(),
@@ -28,68 +28,58 @@ impl Bin {
         }
     }
 }
-fn binary_type(left: Type, op: &Operator, right: Type) -> Result<Type, ParserError> {
-    match left {
-        Type::U32 => match right {
-            Type::U32 => match op.lexeme.as_str() {
-                "+" => Ok(Type::U32),
-                "*" => Ok(Type::U32),
-                "-" => Ok(Type::U32),
-                "/" => Ok(Type::U32),
-                _ => Err(ParserError::new_no_rem(format!(
-                    "Unknown operator `{}`",
-                    op.lexeme
-                ))),
-            },
-            Type::I32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an u32 and an i32 between them".to_string(),
-            )),
-            Type::F32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an u32 and an f32 between them".to_string(),
-            )),
-        },
-        Type::I32 => match right {
-            Type::I32 => match op.lexeme.as_str() {
-                "+" => Ok(Type::I32),
-                "*" => Ok(Type::I32),
-                "-" => Ok(Type::I32),
-                "/" => Ok(Type::I32),
-                _ => Err(ParserError::new_no_rem(format!(
-                    "Unknown operator `{}`",
-                    op.lexeme
-                ))),
-            },
-            Type::U32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an i32 and an u32 between them".to_string(),
-            )),
-            Type::F32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an i32 and an f32 between them".to_string(),
-            )),
-        },
-        Type::F32 => match right {
-            Type::F32 => match op.lexeme.as_str() {
-                "+" => Ok(Type::F32),
-                "*" => Ok(Type::F32),
-                "-" => Ok(Type::F32),
-                "/" => Ok(Type::F32),
-                _ => Err(ParserError::new_no_rem(format!(
-                    "Unknown operator `{}`",
-                    op.lexeme
-                ))),
-            },
-            Type::U32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an f32 and an u32 between them".to_string(),
-            )),
-            Type::I32 => Err(ParserError::new_no_rem(
-                "Cannot call operator an f32 and an i32 between them".to_string(),
+fn origin(bin: &Bin) -> String {
+    match bin {
+        Bin::Bin(Binary { op, expr_type, .. }) => format!(
+            "the left operand is the result of `{}` at {}, which has type `{}`",
+            op.lexeme,
+            op.span.start,
+            expr_type.clone().unwrap()
+        ),
+        Bin::Uno(expr) => format!("the left operand is a `{}` literal", expr.get_type()),
+    }
+}
+fn suffixed(bin: &Bin, expected: &Type) -> Option<String> {
+    let value = match bin {
+        Bin::Uno(Expr::Lit(Literal::Num(Number::I32(value)))) => *value as i64,
+        Bin::Uno(Expr::Lit(Literal::Num(Number::U32(value)))) => *value as i64,
+        _ => return None,
+    };
+    let fits = match expected {
+        Type::I32 => i32::try_from(value).is_ok(),
+        Type::U32 => u32::try_from(value).is_ok(),
+        Type::F32 => true,
+    };
+    fits.then(|| format!("{}{}", value, expected))
+}
+fn binary_type(left: &Bin, op: &Operator, right: &Bin) -> Result<Type, ParserError> {
+    let (left_type, right_type) = (left.get_type(), right.get_type());
+    if left_type != right_type {
+        let error = ParserError::new_with_span(
+            op.span,
+            format!(
+                "Cannot call operator an {} and an {} between them",
+                left_type, right_type
+            ),
+        )
+        .with_note(origin(left));
+        return Err(match suffixed(right, &left_type) {
+            Some(literal) => error.with_help(format!(
+                "write the right operand as `{}` to make it a `{}`",
+                literal, left_type
             )),
-        },
+            None => error,
+        });
+    }
+    match op.lexeme.as_str() {
+        "+" | "*" | "-" | "/" => Ok(left_type),
+        _ => Err(ParserError::new_with_span(
+            op.span,
+            format!("Unknown operator `{}`", op.lexeme),
+        )),
     }
 }
 
-pub fn type_ast() -> Parser<Bin, Bin> {
-    Box::new(|mut bin| {
-        let typed_bin = bin.into_typed()?;
-        Ok(("".to_string(), typed_bin))
-    })
+pub fn type_ast(bin: Bin) -> Result<Bin, ParserError> {
+    bin.to_typed()
 }